@@ -27,15 +27,193 @@ where
     );
 }
 
+/// Parses optional positional argument or returns `default`.
+fn arg_or<T>(args: &[String], idx: usize, default: T) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    Ok(args
+        .get(idx)
+        .map(|s| s.parse())
+        .transpose()?
+        .unwrap_or(default))
+}
+
+/// Runs additional per day modes instead of both parts.
+fn run_mode(ex_num: u8, mode: &str, args: &[String], data: &str) -> Result<()> {
+    match (ex_num, mode) {
+        (2, "report") => {
+            let limit = arg_or(args, 0, 20)?;
+            let radix = arg_or(args, 1, 10)?;
+            let rule = arg_or(args, 2, "at-least-twice".to_string())?;
+            let prod = aoc2025::second::Product::new()
+                .with_radix(radix)?
+                .with_rule(aoc2025::second::rule_by_name(&rule)?);
+            print!("{}", prod.report(data, limit));
+        }
+        (2, "scan") => {
+            let rule = args
+                .first()
+                .ok_or(anyhow!("Usage: 2 scan rule [radix] [threads]"))?;
+            let rule = aoc2025::second::rule_by_name(rule)?;
+            let mut prod = aoc2025::second::Product::new().with_radix(arg_or(args, 1, 10)?)?;
+            if let Some(threads) = args.get(2) {
+                prod = prod.with_threads(threads.parse()?);
+            }
+            let now = Instant::now();
+            let result = prod.with_rule(rule).sum(data);
+            println!(
+                "day_2\tscan\t{}\t{:.3}s",
+                result,
+                now.elapsed().as_secs_f32()
+            );
+        }
+        (3, "explain") => {
+            let digits = arg_or(args, 0, 12)?;
+            println!(
+                "{}",
+                aoc2025::third::JoltageBank::explain_all(data, digits)?
+            );
+        }
+        (3, "budget") => {
+            let budget = args
+                .first()
+                .ok_or(anyhow!("Usage: 3 budget budget [min] [max]"))?
+                .parse()?;
+            let min = arg_or(args, 1, 0)?;
+            let max = arg_or(args, 2, usize::MAX)?;
+            println!(
+                "{}",
+                aoc2025::third::JoltageBank::allocate_budget(data, budget, min, max)?
+            );
+        }
+        (4, "waves") => {
+            let show_frames = args.first().is_some_and(|a| a == "frames");
+            let mut field = aoc2025::fourth::Field::new();
+            field.fill(data);
+            let waves = field.remove_waves();
+            let frames = match show_frames {
+                true => {
+                    field.fill(data);
+                    field.wave_frames()
+                }
+                false => Vec::new(),
+            };
+            for (i, wave) in waves.iter().enumerate() {
+                println!("wave {}\tremoved {}", i + 1, wave.len());
+                if let Some(frame) = frames.get(i) {
+                    println!("{frame}\n");
+                }
+            }
+        }
+        (4, "layout") => {
+            let neighborhood = args
+                .first()
+                .ok_or(anyhow!("Usage: 4 layout neighborhood [threshold] [wrap]"))?
+                .parse()?;
+            let mut field = aoc2025::fourth::Field::new()
+                .with_neighborhood(neighborhood)?
+                .with_threshold(arg_or(args, 1, 4)?)
+                .with_wrap(args.get(2).is_some_and(|a| a == "wrap"));
+            field.fill(data);
+            println!("accessible\t{}", field.accessible());
+            println!("removable\t{}", field.remove_all());
+        }
+        (4, "clusters") => {
+            let diagonal = match arg_or(args, 0, 8)? {
+                4 => false,
+                8 => true,
+                n => return Err(anyhow!("Connectivity must be 4 or 8, got {n}")),
+            };
+            let mut field = aoc2025::fourth::Field::new();
+            field.fill(data);
+            let clusters = field.clusters(diagonal);
+            println!("{clusters}");
+            if args.get(1).is_some_and(|a| a == "grid") {
+                println!("\n{}", clusters.render());
+            }
+        }
+        (5, "coverage") => {
+            let bounds = match (args.first(), args.get(1)) {
+                (Some(lo), Some(hi)) => Some((lo.parse()?, hi.parse()?)),
+                _ => None,
+            };
+            println!("{}", aoc2025::fifth::Database::coverage(data, bounds));
+        }
+        (5, "diff") => {
+            let file = args.first().ok_or(anyhow!("Usage: 5 diff new_file"))?;
+            let new = aoc2025::utils::read_data(ex_num, file)?;
+            println!("{}", aoc2025::fifth::Database::diff(data, &new));
+        }
+        (6, "exact") => {
+            execute(
+                &mut aoc2025::sixth::MathProblem::new().with_exact(true),
+                data,
+            );
+        }
+        (6, "convert") => {
+            let usage = || anyhow!("Usage: 6 convert from to [results]");
+            let from = args.first().ok_or_else(usage)?.parse()?;
+            let to = args.get(1).ok_or_else(usage)?.parse()?;
+            let mut problem = aoc2025::sixth::MathProblem::new();
+            problem.read(data, from)?;
+            print!(
+                "{}",
+                problem.write(to, args.get(2).is_some_and(|a| a == "results"))?
+            );
+        }
+        (6, "breakdown") => {
+            let breakdown = aoc2025::sixth::MathProblem::new().breakdown(data)?;
+            match args.first().is_some_and(|a| a == "json") {
+                true => println!("{}", breakdown.json()),
+                false => println!("{breakdown}"),
+            }
+        }
+        (7, "exits") => {
+            let timelines = aoc2025::seventh::Tachyon::new().timelines(data);
+            println!("left\t{}", timelines.left);
+            for (j, count) in timelines.columns.iter().enumerate() {
+                if *count != num_bigint::BigUint::ZERO {
+                    println!("{j}\t{count}");
+                }
+            }
+            println!("right\t{}", timelines.right);
+            println!("total\t{}", timelines.total());
+        }
+        (7, "sources") => {
+            println!("source\tsplits\ttimelines\tabsorbed");
+            for stats in aoc2025::seventh::Tachyon::new().source_stats(data) {
+                println!(
+                    "{},{}\t{}\t{}\t{}",
+                    stats.row,
+                    stats.col,
+                    stats.splits,
+                    stats.timelines.total(),
+                    stats.absorbed
+                );
+            }
+        }
+        _ => return Err(anyhow!("Mode {mode} not implemented for exercise {ex_num}")),
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<()> {
-    let ex_num = std::env::args()
-        .nth(1)
-        .ok_or(anyhow!("Usage: file ex_num(1-12)"))?
+    let args: Vec<String> = std::env::args().collect();
+    let ex_num = args
+        .get(1)
+        .ok_or(anyhow!("Usage: file ex_num(1-12) [mode [args...]]"))?
         .parse::<u8>()?;
 
     let data = aoc2025::utils::read_data(ex_num, "data")
         .map_err(|e| anyhow!("Exercise not implemented: {e}"))?;
 
+    if let Some(mode) = args.get(2) {
+        return run_mode(ex_num, mode, &args[3..], &data);
+    }
+
     match ex_num {
         1 => execute(&mut aoc2025::first::Dial::new(), &data),
         2 => execute(&mut aoc2025::second::Product::new(), &data),
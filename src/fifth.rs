@@ -1,83 +1,361 @@
+use std::fmt;
+
 use crate::utils::Exercise;
 
+/// Merged fresh ID ranges, sorted and disjoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RangeIndex {
+    /// Inclusive (start, end) pairs, no two of them overlap or touch.
+    ranges: Vec<(u64, u64)>,
+}
+
+impl RangeIndex {
+    /// O(n * log(n))
+    ///
+    /// Merges overlapping and adjacent ranges.
+    pub fn new<I>(ranges: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let mut ranges: Vec<(u64, u64)> = ranges.into_iter().filter(|r| r.0 <= r.1).collect();
+        // sort by start of range then by end
+        ranges.sort_unstable();
+
+        // just merge overlapping ranges
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for next in ranges {
+            match merged.last_mut() {
+                // check only beg > end case because we sorted it
+                Some(curr) if curr.1.saturating_add(1) >= next.0 => {
+                    curr.1 = curr.1.max(next.1);
+                }
+                _ => merged.push(next),
+            }
+        }
+
+        Self { ranges: merged }
+    }
+
+    pub fn ranges(&self) -> &[(u64, u64)] {
+        &self.ranges
+    }
+
+    /// Amount of IDs covered by all ranges.
+    pub fn covered(&self) -> u128 {
+        self.ranges.iter().map(|&(s, e)| (e - s) as u128 + 1).sum()
+    }
+
+    /// Index of the first range ending at or after `id`.
+    fn first_after(&self, id: u64) -> usize {
+        self.ranges.partition_point(|&(_, e)| e < id)
+    }
+
+    /// O(log(ranges))
+    pub fn contains(&self, id: u64) -> bool {
+        self.ranges
+            .get(self.first_after(id))
+            .is_some_and(|&(s, _)| s <= id)
+    }
+
+    /// Uncovered ranges inside inclusive `bounds`.
+    pub fn gaps(&self, bounds: (u64, u64)) -> Vec<(u64, u64)> {
+        let (lo, hi) = bounds;
+        let mut gaps = Vec::new();
+        // first ID not checked yet, None after u64::MAX
+        let mut next = Some(lo);
+        for &(s, e) in self.ranges[self.first_after(lo)..].iter() {
+            let Some(from) = next.filter(|&n| n <= hi) else {
+                break;
+            };
+            if s > from {
+                gaps.push((from, (s - 1).min(hi)));
+            }
+            next = e.checked_add(1);
+        }
+        if let Some(from) = next.filter(|&n| n <= hi) {
+            gaps.push((from, hi));
+        }
+        gaps
+    }
+
+    /// O(ranges + other ranges)
+    ///
+    /// IDs covered by `self` but not by `other`.
+    pub fn difference(&self, other: &RangeIndex) -> RangeIndex {
+        let mut result = Vec::new();
+        let mut j = 0;
+        for &(s, e) in self.ranges.iter() {
+            while j < other.ranges.len() && other.ranges[j].1 < s {
+                j += 1;
+            }
+
+            // first ID of (s, e) not handled yet, None after u64::MAX
+            let mut from = Some(s);
+            let mut k = j;
+            while let Some(cur) = from
+                && k < other.ranges.len()
+                && other.ranges[k].0 <= e
+            {
+                let (os, oe) = other.ranges[k];
+                if os > cur {
+                    result.push((cur, os - 1));
+                }
+                from = oe.checked_add(1);
+                k += 1;
+            }
+            if let Some(cur) = from.filter(|&cur| cur <= e) {
+                result.push((cur, e));
+            }
+        }
+        RangeIndex { ranges: result }
+    }
+
+    /// Fresh status of every ID in `ids`, in the same order.
+    ///
+    /// Ascending IDs gallop forward from the previous range, so a step over
+    /// `d` ranges costs O(log d) and a sorted stream O(ids + ranges).
+    /// Descending steps fall back to binary search, so any stream is
+    /// O(ids * log(ranges)).
+    pub fn contains_all<I>(&self, ids: I) -> Vec<bool>
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        let mut result = Vec::new();
+        let mut cursor = 0;
+        let mut prev = 0;
+        for id in ids {
+            if id < prev {
+                cursor = self.first_after(id);
+            } else {
+                cursor = self.gallop(cursor, id);
+            }
+            prev = id;
+            result.push(self.ranges.get(cursor).is_some_and(|&(s, _)| s <= id));
+        }
+        result
+    }
+
+    /// First range at or after `from` ending at or after `id`.
+    fn gallop(&self, from: usize, id: u64) -> usize {
+        let rest = &self.ranges[from.min(self.ranges.len())..];
+        let mut bound = 1;
+        while bound < rest.len() && rest[bound - 1].1 < id {
+            bound *= 2;
+        }
+        let lo = bound / 2;
+        let hi = bound.min(rest.len());
+        from + lo + rest[lo..hi].partition_point(|r| r.1 < id)
+    }
+}
+
+/// Coverage of IDs by the fresh ranges inside some bounds.
+pub struct Coverage {
+    pub merged: Vec<(u64, u64)>,
+    /// Inclusive interval where gaps are searched, `None` when neither
+    /// given nor spanned by any range.
+    pub bounds: Option<(u64, u64)>,
+    /// Uncovered ranges inside `bounds`.
+    pub gaps: Vec<(u64, u64)>,
+    pub largest_gap: Option<(u64, u64)>,
+    /// Original ranges with whether the other ranges contain them entirely.
+    pub ranges: Vec<((u64, u64), bool)>,
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = |r: &(u64, u64)| (r.1 - r.0) as u128 + 1;
+
+        writeln!(f, "merged {}", self.merged.len())?;
+        for r in self.merged.iter() {
+            writeln!(f, "\t{}-{}\t{}", r.0, r.1, len(r))?;
+        }
+
+        match self.bounds {
+            Some((lo, hi)) => writeln!(f, "gaps {} in {lo}-{hi}", self.gaps.len())?,
+            None => writeln!(f, "gaps 0")?,
+        }
+        for r in self.gaps.iter() {
+            writeln!(f, "\t{}-{}\t{}", r.0, r.1, len(r))?;
+        }
+        if let Some(r) = self.largest_gap {
+            writeln!(f, "largest gap\t{}-{}\t{}", r.0, r.1, len(&r))?;
+        }
+
+        let redundant: Vec<_> = self.ranges.iter().filter(|(_, red)| *red).collect();
+        write!(f, "redundant {}", redundant.len())?;
+        for (r, _) in redundant {
+            write!(f, "\n\t{}-{}", r.0, r.1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Changes of fresh IDs between two databases.
+pub struct RangeDiff {
+    pub became_fresh: Vec<(u64, u64)>,
+    pub became_spoiled: Vec<(u64, u64)>,
+    /// Change of the amount of fresh IDs.
+    pub net_change: i128,
+}
+
+impl fmt::Display for RangeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "became fresh {}", self.became_fresh.len())?;
+        for r in self.became_fresh.iter() {
+            writeln!(f, "\t+{}-{}", r.0, r.1)?;
+        }
+        writeln!(f, "became spoiled {}", self.became_spoiled.len())?;
+        for r in self.became_spoiled.iter() {
+            writeln!(f, "\t-{}-{}", r.0, r.1)?;
+        }
+        write!(f, "net change {:+}", self.net_change)
+    }
+}
+
 pub struct Database;
 
 impl Database {
     pub fn new() -> Self {
         Self {}
     }
-}
 
-impl Exercise for Database {
-    fn day(&self) -> u8 {
-        5
+    /// Fresh ID ranges from the first section of `data`.
+    pub fn parse_ranges(data: &str) -> Vec<(u64, u64)> {
+        let section = data.split("\n\n").next().unwrap_or_default();
+        section
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|range| {
+                range
+                    .trim()
+                    .split_once('-')
+                    .map(|(a, b)| (a.parse().unwrap(), b.parse().unwrap()))
+                    .unwrap()
+            })
+            .collect()
     }
 
-    fn part1(&mut self, data: &str) -> String {
-        let data: Vec<&str> = data.split("\n\n").collect();
-
-        let mut ranges = Vec::new();
-        for range in data[0].split('\n') {
-            let (beg, end): (u64, u64) = range
-                .split_once('-')
-                .map(|(a, b)| (a.parse().unwrap(), b.parse().unwrap()))
-                .unwrap();
-            ranges.push(beg..=end);
-        }
-
-        let mut count = 0;
-        for id in data[1].trim().split('\n') {
-            let id: u64 = id.parse::<u64>().unwrap();
-            if ranges.iter().any(|range| range.contains(&id)) {
-                count += 1;
+    /// O(n * log(n))
+    ///
+    /// Whether every range is fully contained in the union of the others.
+    ///
+    /// Sweeps over range ends: a range is needed only if some IDs are
+    /// covered by it alone. Duplicated ranges are redundant both.
+    pub fn redundant(ranges: &[(u64, u64)]) -> Vec<bool> {
+        let mut events: Vec<(u128, bool, usize)> = Vec::with_capacity(2 * ranges.len());
+        for (i, &(s, e)) in ranges.iter().enumerate() {
+            if s <= e {
+                events.push((s as u128, true, i));
+                events.push((e as u128 + 1, false, i));
             }
         }
+        events.sort_unstable();
 
-        count.to_string()
-    }
-
-    fn part2(&mut self, data: &str) -> String {
-        let data: Vec<&str> = data.split("\n\n").collect();
-
-        let mut ranges: Vec<(u64, u64)> = Vec::new();
-        for range in data[0].split('\n') {
-            let (beg, end): (u64, u64) = range
-                .split_once('-')
-                .map(|(a, b)| (a.parse().unwrap(), b.parse().unwrap()))
-                .unwrap();
-
-            ranges.push((beg, end));
-            // sort by start of range then by end
-            ranges.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
-
-            // just merge overlapping ranges
-            let mut merged = Vec::new();
-            let mut curr = ranges[0];
-            for &next in ranges.iter().skip(1) {
-                // check only beg > end case because we sorted it
-                if curr.1 >= next.0 - 1 {
-                    curr.1 = curr.1.max(next.1);
+        // with one active range the sum of active indices is its index
+        let mut redundant = vec![true; ranges.len()];
+        let (mut active, mut index_sum) = (0, 0);
+        let mut idx = 0;
+        while idx < events.len() {
+            let pos = events[idx].0;
+            while idx < events.len() && events[idx].0 == pos {
+                let (_, start, i) = events[idx];
+                if start {
+                    active += 1;
+                    index_sum += i;
                 } else {
-                    merged.push(curr);
-                    curr = next;
+                    active -= 1;
+                    index_sum -= i;
                 }
+                idx += 1;
+            }
+            if active == 1 {
+                redundant[index_sum] = false;
             }
-            // and replace it after every merge cycle
-            merged.push(curr);
-            ranges = merged;
         }
+        redundant
+    }
+
+    /// Merged ranges, gaps inside `bounds` and redundant ranges of `data`.
+    ///
+    /// Bounds default to the span of all ranges, without ranges and bounds
+    /// there are no gaps.
+    pub fn coverage(data: &str, bounds: Option<(u64, u64)>) -> Coverage {
+        let ranges = Database::parse_ranges(data);
+        let index = RangeIndex::new(ranges.iter().copied());
 
-        ranges
+        let merged = index.ranges();
+        let bounds = bounds.or(match (merged.first(), merged.last()) {
+            (Some(first), Some(last)) => Some((first.0, last.1)),
+            _ => None,
+        });
+        let gaps = bounds.map(|b| index.gaps(b)).unwrap_or_default();
+        let largest_gap = gaps
             .iter()
-            .map(|&(s, e)| e - s + 1)
-            .sum::<u64>()
+            .copied()
+            .max_by(|a, b| (a.1 - a.0).cmp(&(b.1 - b.0)).then(b.0.cmp(&a.0)));
+
+        Coverage {
+            merged: index.ranges().to_vec(),
+            bounds,
+            gaps,
+            largest_gap,
+            ranges: ranges
+                .iter()
+                .copied()
+                .zip(Database::redundant(&ranges))
+                .collect(),
+        }
+    }
+
+    /// Compares fresh ranges of `old` and `new` databases.
+    pub fn diff(old: &str, new: &str) -> RangeDiff {
+        let old = RangeIndex::new(Database::parse_ranges(old));
+        let new = RangeIndex::new(Database::parse_ranges(new));
+
+        RangeDiff {
+            became_fresh: new.difference(&old).ranges,
+            became_spoiled: old.difference(&new).ranges,
+            net_change: new.covered() as i128 - old.covered() as i128,
+        }
+    }
+
+    /// Available IDs from the second section of `data`.
+    pub fn parse_ids(data: &str) -> Vec<u64> {
+        let section = data.split("\n\n").nth(1).unwrap_or_default();
+        section
+            .split_whitespace()
+            .map(|id| id.parse().unwrap())
+            .collect()
+    }
+}
+
+impl Exercise for Database {
+    fn day(&self) -> u8 {
+        5
+    }
+
+    fn part1(&mut self, data: &str) -> String {
+        let index = RangeIndex::new(Database::parse_ranges(data));
+        let ids = Database::parse_ids(data);
+
+        index
+            .contains_all(ids)
+            .into_iter()
+            .filter(|&fresh| fresh)
+            .count()
+            .to_string()
+    }
+
+    fn part2(&mut self, data: &str) -> String {
+        RangeIndex::new(Database::parse_ranges(data))
+            .covered()
             .to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::read_data;
+    use crate::utils::{Rng, read_data};
 
     use super::*;
 
@@ -96,4 +374,162 @@ mod tests {
         let result = db.part2(&data);
         assert_eq!(result, "14");
     }
+
+    #[test]
+    fn fifth_test_index_matches_linear_scan() {
+        let mut rng = Rng(0x6a09e667f3bcc908);
+        for _ in 0..200 {
+            let ranges: Vec<(u64, u64)> = (0..rng.next(20))
+                .map(|_| {
+                    let s = rng.next(200);
+                    (s, s + rng.next(15))
+                })
+                .collect();
+            let index = RangeIndex::new(ranges.iter().copied());
+            assert!(index.ranges().windows(2).all(|w| w[0].1 + 1 < w[1].0));
+
+            let mut ids: Vec<u64> = (0..100).map(|_| rng.next(230)).collect();
+            let linear = |ids: &[u64]| -> Vec<bool> {
+                ids.iter()
+                    .map(|id| ranges.iter().any(|r| (r.0..=r.1).contains(id)))
+                    .collect()
+            };
+
+            assert_eq!(index.contains_all(ids.iter().copied()), linear(&ids));
+            for (&id, fresh) in ids.iter().zip(linear(&ids)) {
+                assert_eq!(index.contains(id), fresh);
+            }
+            ids.sort();
+            assert_eq!(index.contains_all(ids.iter().copied()), linear(&ids));
+        }
+    }
+
+    #[test]
+    fn fifth_test_index_alternating_stream() {
+        // walking forward one range at a time would take ids * ranges steps
+        let index = RangeIndex::new((0..200_000u64).map(|i| (i * 10, i * 10 + 4)));
+        let ids: Vec<u64> = (0..20_000u64)
+            .map(|i| match i % 2 {
+                0 => i % 7,
+                _ => u64::MAX - i,
+            })
+            .collect();
+        let fresh = index.contains_all(ids.iter().copied());
+        for (&id, fresh) in ids.iter().zip(fresh) {
+            assert_eq!(index.contains(id), fresh);
+        }
+        assert_eq!(
+            index.contains_all([0, 1_999_994, 5, 1_999_995, 15, u64::MAX]),
+            vec![true, true, false, false, false, false]
+        );
+    }
+
+    #[test]
+    fn fifth_test_index_edges() {
+        let index = RangeIndex::new([(0, 0), (1, 3), (u64::MAX - 1, u64::MAX), (9, 7)]);
+        assert_eq!(index.ranges(), &[(0, 3), (u64::MAX - 1, u64::MAX)]);
+        assert_eq!(index.covered(), 6);
+        assert_eq!(
+            index.contains_all([u64::MAX, 0, 4, 3]),
+            vec![true, true, false, true]
+        );
+        assert_eq!(
+            RangeIndex::new([(0, u64::MAX)]).covered(),
+            u64::MAX as u128 + 1
+        );
+    }
+
+    #[test]
+    fn fifth_test_gaps() {
+        let index = RangeIndex::new([(3, 5), (10, 14), (16, 20), (12, 18)]);
+        assert_eq!(index.gaps((0, 25)), vec![(0, 2), (6, 9), (21, 25)]);
+        assert_eq!(index.gaps((4, 12)), vec![(6, 9)]);
+        assert_eq!(index.gaps((11, 13)), vec![]);
+        assert_eq!(index.gaps((7, 7)), vec![(7, 7)]);
+
+        let index = RangeIndex::new([(0, 1), (u64::MAX, u64::MAX)]);
+        assert_eq!(index.gaps((0, u64::MAX)), vec![(2, u64::MAX - 1)]);
+
+        let data = read_data(5, "test1").unwrap();
+        let coverage = Database::coverage(&data, None);
+        assert_eq!(coverage.merged, vec![(3, 5), (10, 20)]);
+        assert_eq!(coverage.bounds, Some((3, 20)));
+        assert_eq!(coverage.largest_gap, Some((6, 9)));
+        assert!(coverage.ranges.iter().all(|(_, red)| !red));
+
+        let coverage = Database::coverage("\n\n1\n", None);
+        assert_eq!(coverage.bounds, None);
+        assert!(coverage.gaps.is_empty());
+        assert_eq!(coverage.largest_gap, None);
+        let coverage = Database::coverage("\n\n1\n", Some((4, 9)));
+        assert_eq!(coverage.gaps, vec![(4, 9)]);
+    }
+
+    #[test]
+    fn fifth_test_redundant_matches_brute_force() {
+        let mut rng = Rng(0xbb67ae8584caa73b);
+        for _ in 0..300 {
+            let ranges: Vec<(u64, u64)> = (0..rng.next(12))
+                .map(|_| {
+                    let s = rng.next(60);
+                    (s, s + rng.next(12))
+                })
+                .collect();
+
+            let brute: Vec<bool> = (0..ranges.len())
+                .map(|i| {
+                    let others = RangeIndex::new(
+                        ranges
+                            .iter()
+                            .enumerate()
+                            .filter(|&(j, _)| j != i)
+                            .map(|(_, &r)| r),
+                    );
+                    (ranges[i].0..=ranges[i].1).all(|id| others.contains(id))
+                })
+                .collect();
+            assert_eq!(Database::redundant(&ranges), brute, "{ranges:?}");
+        }
+    }
+
+    #[test]
+    fn fifth_test_diff_matches_brute_force() {
+        let mut rng = Rng(0x3c6ef372fe94f82b);
+        let random_ranges = |rng: &mut Rng| -> Vec<(u64, u64)> {
+            (0..rng.next(8))
+                .map(|_| {
+                    let s = rng.next(80);
+                    (s, s + rng.next(20))
+                })
+                .collect()
+        };
+        let to_data = |ranges: &[(u64, u64)]| -> String {
+            let lines: Vec<_> = ranges.iter().map(|r| format!("{}-{}", r.0, r.1)).collect();
+            format!("{}\n\n1\n", lines.join("\n"))
+        };
+
+        for _ in 0..300 {
+            let (old, new) = (random_ranges(&mut rng), random_ranges(&mut rng));
+            let diff = Database::diff(&to_data(&old), &to_data(&new));
+
+            let (old, new) = (RangeIndex::new(old), RangeIndex::new(new));
+            let fresh = RangeIndex::new(diff.became_fresh.iter().copied());
+            let spoiled = RangeIndex::new(diff.became_spoiled.iter().copied());
+            assert_eq!(fresh.ranges(), diff.became_fresh);
+            assert_eq!(spoiled.ranges(), diff.became_spoiled);
+            for id in 0..110 {
+                assert_eq!(fresh.contains(id), new.contains(id) && !old.contains(id));
+                assert_eq!(spoiled.contains(id), old.contains(id) && !new.contains(id));
+            }
+            assert_eq!(
+                diff.net_change,
+                fresh.covered() as i128 - spoiled.covered() as i128
+            );
+        }
+
+        let all = RangeIndex::new([(0, u64::MAX)]);
+        let hole = RangeIndex::new([(5, 6), (u64::MAX, u64::MAX)]);
+        assert_eq!(all.difference(&hole).ranges(), &[(0, 4), (7, u64::MAX - 1)]);
+        assert_eq!(hole.difference(&all).ranges(), &[]);
+    }
 }
@@ -1,37 +1,431 @@
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use anyhow::{Result, anyhow};
+
 use crate::utils::Exercise;
 
+/// Rolls with less neighbors are accessible by forklifts.
+const ADJ_ROLLS: u32 = 4;
+
+/// Cells counted as neighbors of a roll.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// 4 orthogonal cells.
+    VonNeumann,
+    /// 8 surrounding cells.
+    Moore,
+    /// All cells within Chebyshev distance `r`.
+    Radius(u32),
+    /// Arbitrary (dy, dx) offsets.
+    Custom(Vec<(i32, i32)>),
+}
+
+impl Neighborhood {
+    /// Offsets as (dy, dx).
+    pub fn offsets(&self) -> Vec<(i32, i32)> {
+        match self {
+            Neighborhood::VonNeumann => vec![(-1, 0), (0, 1), (1, 0), (0, -1)],
+            Neighborhood::Moore => Neighborhood::Radius(1).offsets(),
+            Neighborhood::Radius(r) => {
+                let r = *r as i32;
+                (-r..=r)
+                    .flat_map(|dy| (-r..=r).map(move |dx| (dy, dx)))
+                    .filter(|&off| off != (0, 0))
+                    .collect()
+            }
+            Neighborhood::Custom(offsets) => offsets.clone(),
+        }
+    }
+
+    /// Number of offsets, without building them.
+    fn size(&self) -> u64 {
+        match self {
+            Neighborhood::VonNeumann => 4,
+            Neighborhood::Moore => 8,
+            Neighborhood::Radius(r) => (2 * *r as u64 + 1).saturating_pow(2) - 1,
+            Neighborhood::Custom(offsets) => offsets.len() as u64,
+        }
+    }
+}
+
+impl FromStr for Neighborhood {
+    type Err = anyhow::Error;
+
+    /// Parses `von-neumann`, `moore`, `radius:R` or `custom:DY,DX;DY,DX...`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "von-neumann" => Ok(Neighborhood::VonNeumann),
+            "moore" => Ok(Neighborhood::Moore),
+            _ => {
+                if let Some(r) = s.strip_prefix("radius:") {
+                    return Ok(Neighborhood::Radius(r.parse()?));
+                }
+                let offsets = s
+                    .strip_prefix("custom:")
+                    .ok_or(anyhow!("Unknown neighborhood {s}"))?;
+                let offsets = offsets
+                    .split(';')
+                    .map(|off| {
+                        let (dy, dx) =
+                            off.split_once(',').ok_or(anyhow!("Invalid offset {off}"))?;
+                        Ok((dy.trim().parse()?, dx.trim().parse()?))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Neighborhood::Custom(offsets))
+            }
+        }
+    }
+}
+
+/// Row-major bit-packed grid, every row starts at a new word.
+///
+/// Bit `j % 64` of word `j / 64` of a row is column `j`,
+/// bits past the width are always zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BitGrid {
+    height: usize,
+    width: usize,
+    /// Words per row.
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(height: usize, width: usize) -> Self {
+        let stride = width.div_ceil(64);
+        Self {
+            height,
+            width,
+            stride,
+            words: vec![0; height * stride],
+        }
+    }
+
+    /// Grid with cells set where `lines` have `mark`, rows split like
+    /// `str::lines`. Reads the input once, 8 bytes at a time without
+    /// branching per byte, and finds line ends in the same pass. Rows are laid
+    /// out with the stride of the longest row seen so far and moved only when
+    /// a longer row shows up.
+    pub fn from_lines(lines: &str, mark: u8) -> Self {
+        let bytes = lines.as_bytes();
+        let mut grid = BitGrid::default();
+        let mut row = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            row.clear();
+            let mut len = 0;
+            let ended = loop {
+                let chunk = &bytes[pos + len..bytes.len().min(pos + len + 64)];
+                let (cells, newlines) = pack_word(chunk, mark);
+                if newlines == 0 && chunk.len() == 64 {
+                    row.push(cells);
+                    len += 64;
+                    if pos + len == bytes.len() {
+                        break false;
+                    }
+                    continue;
+                }
+                let n = match newlines {
+                    0 => chunk.len(),
+                    _ => newlines.trailing_zeros() as usize,
+                };
+                if n > 0 {
+                    row.push(cells & (u64::MAX >> (64 - n)));
+                }
+                len += n;
+                break newlines != 0;
+            };
+            pos += len + ended as usize;
+            if ended && len > 0 && bytes[pos - 2] == b'\r' {
+                len -= 1;
+                row[len / 64] &= !(1 << (len % 64));
+            }
+
+            if len > grid.width {
+                grid.widen(len);
+            }
+            row.resize(grid.stride, 0);
+            grid.words.extend_from_slice(&row);
+            grid.height += 1;
+        }
+        grid
+    }
+
+    /// Moves rows to the stride of `width` columns.
+    fn widen(&mut self, width: usize) {
+        let stride = width.div_ceil(64);
+        if stride != self.stride && self.height > 0 {
+            let mut words = vec![0; self.height * stride];
+            for (i, row) in self.words.chunks(self.stride.max(1)).enumerate() {
+                words[i * stride..i * stride + row.len()].copy_from_slice(row);
+            }
+            self.words = words;
+        }
+        self.width = width;
+        self.stride = stride;
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> bool {
+        (self.words[i * self.stride + j / 64] >> (j % 64)) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, value: bool) {
+        let word = &mut self.words[i * self.stride + j / 64];
+        if value {
+            *word |= 1 << (j % 64);
+        } else {
+            *word &= !(1 << (j % 64));
+        }
+    }
+
+    pub fn row(&self, i: usize) -> &[u64] {
+        &self.words[i * self.stride..(i + 1) * self.stride]
+    }
+
+    pub fn count_ones(&self) -> u64 {
+        self.words.iter().map(|w| w.count_ones() as u64).sum()
+    }
+
+    /// Writes row `i` moved by `dx` columns into `out`,
+    /// so bit `j` of `out` is cell (i, j + dx).
+    fn shifted_row(&self, i: usize, dx: i64, wrap: bool, out: &mut [u64]) {
+        out.fill(0);
+        let src = self.row(i);
+        let w = self.width as i64;
+        if wrap && w > 0 {
+            // cells j + r past the width come from the row start
+            let r = dx.rem_euclid(w) as usize;
+            shr_or(src, r, out);
+            if r > 0 {
+                shl_or(src, self.width - r, out);
+            }
+        } else if dx >= 0 {
+            shr_or(src, dx as usize, out);
+        } else {
+            shl_or(src, dx.unsigned_abs() as usize, out);
+        }
+        if let Some(last) = out.last_mut()
+            && !self.width.is_multiple_of(64)
+        {
+            *last &= (1 << (self.width % 64)) - 1;
+        }
+    }
+}
+
+/// Bits of up to 64 bytes of `chunk` equal to `mark` and to `\n`,
+/// bit `b` stands for byte `b`.
+fn pack_word(chunk: &[u8], mark: u8) -> (u64, u64) {
+    const ONES: u64 = 0x0101_0101_0101_0101;
+    const HIGH: u64 = 0x8080_8080_8080_8080;
+    let mut cells = 0;
+    // some byte below b' ', line ends are rare so they are found separately
+    let mut control = 0;
+    let mut groups = chunk.chunks_exact(8);
+    for (k, group) in groups.by_ref().enumerate() {
+        let bytes = u64::from_le_bytes(group.try_into().unwrap());
+        cells |= equal_bytes(bytes, mark) << (8 * k);
+        control |= bytes.wrapping_sub(ONES * b' ' as u64) & !bytes & HIGH;
+    }
+    let rest = groups.remainder();
+    if !rest.is_empty() {
+        // padding matches neither `mark` nor `\n`
+        let mut buf = [(mark == 0) as u8; 8];
+        buf[..rest.len()].copy_from_slice(rest);
+        cells |= equal_bytes(u64::from_le_bytes(buf), mark) << (chunk.len() / 8 * 8);
+        control = 1;
+    }
+
+    let mut newlines = 0;
+    if control != 0 {
+        for (b, &c) in chunk.iter().enumerate() {
+            newlines |= ((c == b'\n') as u64) << b;
+        }
+    }
+    (cells, newlines)
+}
+
+/// Bit `b` is set when byte `b` of `bytes` equals `c`.
+fn equal_bytes(bytes: u64, c: u8) -> u64 {
+    const LOW: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+    let x = bytes ^ u64::from_ne_bytes([c; 8]);
+    // high bit of every zero byte
+    let zero = !(((x & LOW) + LOW) | x | LOW);
+    // gather high bits into the top byte
+    ((zero >> 7).wrapping_mul(0x0102_0408_1020_4080)) >> 56
+}
+
+/// `out |= src >> k` for words as one little-endian number.
+fn shr_or(src: &[u64], k: usize, out: &mut [u64]) {
+    let (q, b) = (k / 64, k % 64);
+    for (w, o) in out.iter_mut().enumerate() {
+        let lo = src.get(w + q).copied().unwrap_or(0);
+        let hi = src.get(w + q + 1).copied().unwrap_or(0);
+        *o |= if b == 0 {
+            lo
+        } else {
+            (lo >> b) | (hi << (64 - b))
+        };
+    }
+}
+
+/// `out |= src << k` for words as one little-endian number.
+fn shl_or(src: &[u64], k: usize, out: &mut [u64]) {
+    let (q, b) = (k / 64, k % 64);
+    for (w, o) in out.iter_mut().enumerate().skip(q) {
+        let hi = src[w - q];
+        let lo = if w > q { src[w - q - 1] } else { 0 };
+        *o |= if b == 0 {
+            hi
+        } else {
+            (hi << b) | (lo >> (64 - b))
+        };
+    }
+}
+
+/// Connected group of rolls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    pub size: usize,
+    /// (top, left, bottom, right) inclusive.
+    pub bounds: (usize, usize, usize, usize),
+    /// Rolls of the cluster accessible right now.
+    pub accessible: usize,
+    /// Whether removing accessible rolls until none left (part 2)
+    /// takes every roll of the cluster.
+    pub vanishes: bool,
+}
+
+/// Clusters of rolls with every cell labelled by its cluster index.
+pub struct Clusters {
+    pub clusters: Vec<Cluster>,
+    /// Cluster index of every cell, row by row.
+    pub labels: Vec<Option<usize>>,
+    width: usize,
+}
+
+impl Clusters {
+    /// Label drawn for cluster `idx`, repeats after 62 clusters.
+    pub fn label(idx: usize) -> char {
+        const LABELS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        LABELS[idx % LABELS.len()] as char
+    }
+
+    /// Renders field with cluster labels instead of rolls.
+    pub fn render(&self) -> String {
+        if self.width == 0 {
+            return String::new();
+        }
+        self.labels
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|l| l.map_or('.', Clusters::label))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Display for Clusters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "label\tsize\ttop\tleft\tbottom\tright\taccessible\tvanishes"
+        )?;
+        for (idx, c) in self.clusters.iter().enumerate() {
+            let (top, left, bottom, right) = c.bounds;
+            writeln!(
+                f,
+                "{}\t{}\t{top}\t{left}\t{bottom}\t{right}\t{}\t{}",
+                Clusters::label(idx),
+                c.size,
+                c.accessible,
+                c.vanishes
+            )?;
+        }
+        write!(
+            f,
+            "clusters {}, vanishing {}",
+            self.clusters.len(),
+            self.clusters.iter().filter(|c| c.vanishes).count()
+        )
+    }
+}
+
+#[derive(Clone)]
 pub struct Field {
-    pub data: Vec<Vec<bool>>,
+    pub data: BitGrid,
+    /// Neighbor offsets as (dy, dx).
+    offsets: Vec<(i32, i32)>,
+    /// Rolls with less neighbor rolls are accessible.
+    threshold: u32,
+    /// Whether neighbors wrap around field edges (torus).
+    wrap: bool,
 }
 
 impl Field {
     pub fn new() -> Self {
-        Self { data: Vec::new() }
+        Self {
+            data: BitGrid::default(),
+            offsets: Neighborhood::Moore.offsets(),
+            threshold: ADJ_ROLLS,
+            wrap: false,
+        }
+    }
+
+    /// Neighbor amounts are kept in `u16`, larger neighborhoods are an error.
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Result<Self> {
+        if neighborhood.size() > u16::MAX as u64 {
+            return Err(anyhow!(
+                "Neighborhood must have at most {} offsets, got {}",
+                u16::MAX,
+                neighborhood.size()
+            ));
+        }
+        self.offsets = neighborhood.offsets();
+        Ok(self)
+    }
+
+    pub fn with_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    fn width(&self) -> usize {
+        self.data.width()
+    }
+
+    /// Cell at (y + dy, x + dx), wrapped around edges if enabled.
+    fn shifted(&self, y: usize, x: usize, dy: i32, dx: i32) -> Option<(usize, usize)> {
+        let (h, w) = (self.data.height() as i64, self.width() as i64);
+        let (mut i, mut j) = (y as i64 + dy as i64, x as i64 + dx as i64);
+        if self.wrap {
+            i = i.rem_euclid(h);
+            j = j.rem_euclid(w);
+        }
+        (0 <= i && i < h && 0 <= j && j < w).then_some((i as usize, j as usize))
     }
 
     fn check_adj(&self, x: i32, y: i32) -> u32 {
         let mut count = 0;
 
-        let directions = [
-            (-1, 0),
-            (-1, 1),
-            (0, 1),
-            (1, 1),
-            (1, 0),
-            (1, -1),
-            (0, -1),
-            (-1, -1),
-        ];
-
-        for (dy, dx) in directions.iter() {
-            let i = y + dy;
-            let j = x + dx;
-
-            if i >= 0
-                && i < self.data.len() as i32
-                && j >= 0
-                && j < self.data[i as usize].len() as i32
-                && self.data[i as usize][j as usize]
+        for &(dy, dx) in self.offsets.iter() {
+            if let Some((i, j)) = self.shifted(y as usize, x as usize, dy, dx)
+                && self.data.get(i, j)
             {
                 count += 1;
             }
@@ -39,67 +433,355 @@ impl Field {
         count
     }
 
-    fn fill(&mut self, data: &str) {
-        self.data.clear();
-        for line in data.lines() {
-            self.data.push(Vec::new());
-            for c in line.chars() {
-                self.data.last_mut().unwrap().push(c == '@');
+    /// Cells which have (y, x) as a neighbor.
+    ///
+    /// Differs from neighbors of (y, x) for asymmetric custom offsets.
+    fn neighbors(&self, y: usize, x: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.offsets
+            .iter()
+            .filter_map(move |&(dy, dx)| self.shifted(y, x, -dy, -dx))
+    }
+
+    /// Bit-sliced amounts of neighbor rolls for row `i`:
+    /// bit `j` of `planes[b]` is bit `b` of the amount for cell (i, j).
+    ///
+    /// Adds shifted neighbor rows word by word with ripple carry,
+    /// so 64 cells are counted at once.
+    fn count_row(&self, i: usize, planes: &mut [Vec<u64>], tmp: &mut [u64]) {
+        for plane in planes.iter_mut() {
+            plane.fill(0);
+        }
+
+        let h = self.data.height() as i64;
+        for &(dy, dx) in self.offsets.iter() {
+            let mut src = i as i64 + dy as i64;
+            if self.wrap {
+                src = src.rem_euclid(h);
+            } else if src < 0 || src >= h {
+                continue;
+            }
+            self.data
+                .shifted_row(src as usize, dx as i64, self.wrap, tmp);
+
+            // ripple-carry add of `tmp` into the planes, a plane at a time
+            for plane in planes.iter_mut() {
+                for (p, carry) in plane.iter_mut().zip(tmp.iter_mut()) {
+                    let sum = *p ^ *carry;
+                    *carry &= *p;
+                    *p = sum;
+                }
             }
         }
     }
-}
 
-impl Exercise for Field {
-    fn day(&self) -> u8 {
-        4
+    /// Bit planes (and one row of scratch) for `count_row`.
+    fn count_buffers(&self) -> (Vec<Vec<u64>>, Vec<u64>) {
+        let bits = (usize::BITS - self.offsets.len().leading_zeros()).max(1) as usize;
+        let stride = self.width().div_ceil(64);
+        (vec![vec![0; stride]; bits], vec![0; stride])
     }
 
-    fn part1(&mut self, data: &str) -> String {
-        const ADJ_ROLLS: u32 = 4;
+    /// Amounts of neighbor rolls for all cells, row by row.
+    fn neighbor_counts(&self) -> Vec<u16> {
+        let (h, w) = (self.data.height(), self.width());
+        let (mut planes, mut tmp) = self.count_buffers();
+        let mut counts = vec![0; h * w];
+        for i in 0..h {
+            self.count_row(i, &mut planes, &mut tmp);
+            for (j, count) in counts[i * w..(i + 1) * w].iter_mut().enumerate() {
+                for (b, plane) in planes.iter().enumerate() {
+                    *count |= (((plane[j / 64] >> (j % 64)) & 1) as u16) << b;
+                }
+            }
+        }
+        counts
+    }
 
-        self.fill(data);
+    /// O(cells * offsets / 64)
+    ///
+    /// Amount of rolls accessible right now.
+    ///
+    /// Compares bit-sliced neighbor amounts with the threshold
+    /// for 64 cells at once and counts matching rolls with popcount.
+    pub fn accessible(&self) -> u64 {
+        let (mut planes, mut tmp) = self.count_buffers();
+        let mut total = 0;
+        for i in 0..self.data.height() {
+            self.count_row(i, &mut planes, &mut tmp);
+            for (w, &rolls) in self.data.row(i).iter().enumerate() {
+                if rolls == 0 {
+                    continue;
+                }
+                // lanes with amount < threshold, from the highest bit
+                let mut less = 0;
+                let mut equal = !0u64;
+                if (self.threshold as u64) >> planes.len() != 0 {
+                    less = !0;
+                } else {
+                    for (b, plane) in planes.iter().enumerate().rev() {
+                        if (self.threshold >> b) & 1 == 1 {
+                            less |= equal & !plane[w];
+                            equal &= plane[w];
+                        } else {
+                            equal &= !plane[w];
+                        }
+                    }
+                }
+                total += (rolls & less).count_ones() as u64;
+            }
+        }
+        total
+    }
 
+    /// O(cells * offsets)
+    ///
+    /// Reference for `accessible`, checks neighbors of every cell.
+    pub fn accessible_slow(&self) -> u64 {
         let mut total = 0;
-        for i in 0..self.data.len() {
-            for j in 0..self.data[i].len() {
-                if self.data[i][j] && self.check_adj(j as i32, i as i32) < ADJ_ROLLS {
+        for i in 0..self.data.height() {
+            for j in 0..self.width() {
+                if self.data.get(i, j) && self.check_adj(j as i32, i as i32) < self.threshold {
                     total += 1;
                 }
             }
         }
-
-        total.to_string()
+        total
     }
 
-    fn part2(&mut self, data: &str) -> String {
-        const ADJ_ROLLS: u32 = 4;
+    /// O(cells * offsets)
+    ///
+    /// Removes accessible rolls until there are none and returns their amount.
+    ///
+    /// Keeps amount of neighbor rolls for every roll and a queue of rolls
+    /// which became accessible, so every roll is removed once and only
+    /// its neighbors are updated (k-core peeling).
+    pub fn remove_all(&mut self) -> u64 {
+        let w = self.width();
+        let mut adj = self.neighbor_counts();
+        let mut queue = VecDeque::new();
+        for i in 0..self.data.height() {
+            for j in 0..w {
+                if self.data.get(i, j) && (adj[i * w + j] as u32) < self.threshold {
+                    queue.push_back((i, j));
+                }
+            }
+        }
 
-        self.fill(data);
+        let mut total = 0;
+        while let Some((i, j)) = queue.pop_front() {
+            if !self.data.get(i, j) {
+                continue;
+            }
+            self.data.set(i, j, false);
+            total += 1;
+
+            for (ni, nj) in self.neighbors(i, j) {
+                let count = &mut adj[ni * w + nj];
+                *count -= 1;
+                // queue roll only once, when it crosses the threshold
+                if self.data.get(ni, nj) && *count as u32 + 1 == self.threshold {
+                    queue.push_back((ni, nj));
+                }
+            }
+        }
+
+        total
+    }
 
-        // remove until we can
+    /// O(passes * cells)
+    ///
+    /// Reference for `remove_all`, rescans whole field until nothing is removed.
+    pub fn remove_all_slow(&mut self) -> u64 {
         let mut total = 0;
         let mut removed = true;
         while removed {
             removed = false;
-            for i in 0..self.data.len() {
-                for j in 0..self.data[i].len() {
-                    if self.data[i][j] && self.check_adj(j as i32, i as i32) < ADJ_ROLLS {
-                        self.data[i][j] = false;
+            for i in 0..self.data.height() {
+                for j in 0..self.width() {
+                    if self.data.get(i, j) && self.check_adj(j as i32, i as i32) < self.threshold {
+                        self.data.set(i, j, false);
                         total += 1;
                         removed = true;
                     }
                 }
             }
         }
+        total
+    }
 
-        total.to_string()
+    /// O(cells * offsets)
+    ///
+    /// Removes rolls in synchronous waves and returns (row, col) of rolls
+    /// removed by every wave.
+    ///
+    /// Each wave takes all rolls accessible at its start at once. It differs
+    /// from `remove_all_slow`, which removes rolls in scan order, so rolls
+    /// later in a pass already see earlier removals. Total amount of removed
+    /// rolls is the same as in `part2`, only their grouping differs.
+    pub fn remove_waves(&mut self) -> Vec<Vec<(usize, usize)>> {
+        let mut waves = Vec::new();
+        self.peel_waves(|_, wave| waves.push(wave.to_vec()));
+        waves
+    }
+
+    /// O(cells * offsets)
+    ///
+    /// Runs synchronous waves, calling `on_wave` with the field
+    /// and rolls removed right after every wave.
+    fn peel_waves<F>(&mut self, mut on_wave: F)
+    where
+        F: FnMut(&Field, &[(usize, usize)]),
+    {
+        let w = self.width();
+        let mut adj = self.neighbor_counts();
+        let mut wave = Vec::new();
+        for i in 0..self.data.height() {
+            for j in 0..w {
+                if self.data.get(i, j) && (adj[i * w + j] as u32) < self.threshold {
+                    wave.push((i, j));
+                }
+            }
+        }
+
+        while !wave.is_empty() {
+            for &(i, j) in wave.iter() {
+                self.data.set(i, j, false);
+            }
+
+            // only neighbors of removed rolls may become accessible
+            let mut next = Vec::new();
+            for &(i, j) in wave.iter() {
+                for (ni, nj) in self.neighbors(i, j) {
+                    let count = &mut adj[ni * w + nj];
+                    *count -= 1;
+                    if self.data.get(ni, nj) && *count as u32 + 1 == self.threshold {
+                        next.push((ni, nj));
+                    }
+                }
+            }
+            next.sort();
+
+            on_wave(self, &wave);
+            wave = next;
+        }
+    }
+
+    /// Renders field with input characters: `@` for rolls, `.` for empty cells.
+    pub fn render(&self) -> String {
+        (0..self.data.height())
+            .map(|i| {
+                (0..self.width())
+                    .map(|j| if self.data.get(i, j) { '@' } else { '.' })
+                    .collect()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Removes rolls in synchronous waves (see `remove_waves`) and renders
+    /// field after every wave.
+    pub fn wave_frames(&mut self) -> Vec<String> {
+        let mut frames = Vec::new();
+        self.peel_waves(|field, _| frames.push(field.render()));
+        frames
+    }
+
+    /// O(cells * offsets)
+    ///
+    /// Finds groups of rolls connected orthogonally (`diagonal == false`)
+    /// or through all 8 directions, in scan order of their first roll.
+    pub fn clusters(&self, diagonal: bool) -> Clusters {
+        let (h, w) = (self.data.height(), self.width());
+        let steps = match diagonal {
+            true => Neighborhood::Moore.offsets(),
+            false => Neighborhood::VonNeumann.offsets(),
+        };
+
+        let mut labels: Vec<Option<usize>> = vec![None; h * w];
+        let mut clusters = Vec::new();
+        for i in 0..h {
+            for j in 0..w {
+                if !self.data.get(i, j) || labels[i * w + j].is_some() {
+                    continue;
+                }
+
+                let idx = clusters.len();
+                let mut cluster = Cluster {
+                    size: 0,
+                    bounds: (i, j, i, j),
+                    accessible: 0,
+                    vanishes: false,
+                };
+                labels[i * w + j] = Some(idx);
+                let mut queue = VecDeque::from([(i, j)]);
+                while let Some((y, x)) = queue.pop_front() {
+                    cluster.size += 1;
+                    let (top, left, bottom, right) = cluster.bounds;
+                    cluster.bounds = (top.min(y), left.min(x), bottom.max(y), right.max(x));
+
+                    for &(dy, dx) in steps.iter() {
+                        if let Some((ny, nx)) = self.shifted(y, x, dy, dx)
+                            && self.data.get(ny, nx)
+                            && labels[ny * w + nx].is_none()
+                        {
+                            labels[ny * w + nx] = Some(idx);
+                            queue.push_back((ny, nx));
+                        }
+                    }
+                }
+                clusters.push(cluster);
+            }
+        }
+
+        let counts = self.neighbor_counts();
+        let mut peeled = self.clone();
+        peeled.remove_all();
+        let mut left = vec![0; clusters.len()];
+        for (cell, label) in labels.iter().enumerate() {
+            let Some(idx) = *label else { continue };
+            if (counts[cell] as u32) < self.threshold {
+                clusters[idx].accessible += 1;
+            }
+            if peeled.data.get(cell / w, cell % w) {
+                left[idx] += 1;
+            }
+        }
+        for (cluster, left) in clusters.iter_mut().zip(left) {
+            cluster.vanishes = left == 0;
+        }
+
+        Clusters {
+            clusters,
+            labels,
+            width: w,
+        }
+    }
+
+    /// Rows shorter than the longest one are padded with empty cells.
+    pub fn fill(&mut self, data: &str) {
+        self.data = BitGrid::from_lines(data, b'@');
+    }
+}
+
+impl Exercise for Field {
+    fn day(&self) -> u8 {
+        4
+    }
+
+    fn part1(&mut self, data: &str) -> String {
+        self.fill(data);
+        self.accessible().to_string()
+    }
+
+    fn part2(&mut self, data: &str) -> String {
+        self.fill(data);
+        self.remove_all().to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::read_data;
+    use crate::utils::{Rng, read_data};
 
     use super::*;
 
@@ -125,4 +807,241 @@ mod tests {
         let result = field.part2(&data);
         assert_eq!(result, "9");
     }
+
+    /// Random field with roughly `density` percent of rolls.
+    fn random_field(rng: &mut Rng, density: u64) -> String {
+        let (h, w) = (1 + rng.next(30), 1 + rng.next(30));
+        (0..h)
+            .map(|_| {
+                (0..w)
+                    .map(|_| if rng.next(100) < density { '@' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn fourth_test_peeling_matches_slow() {
+        let mut rng = Rng(0x853c49e6748fea9b);
+        for case in 0..300 {
+            let data = random_field(&mut rng, case % 100);
+            let mut fast = Field::new();
+            let mut slow = Field::new();
+            fast.fill(&data);
+            slow.fill(&data);
+            assert_eq!(fast.remove_all(), slow.remove_all_slow(), "{data}");
+            assert_eq!(fast.data, slow.data);
+        }
+    }
+
+    #[test]
+    fn fourth_test_waves() {
+        let mut field = Field::new();
+        field.fill(&read_data(field.day(), "test2").unwrap());
+        let waves = field.remove_waves();
+        let counts: Vec<_> = waves.iter().map(|w| w.len()).collect();
+        assert_eq!(counts, vec![4, 4, 1]);
+        assert_eq!(waves[2], vec![(1, 1)]);
+
+        field.fill(&read_data(field.day(), "test2").unwrap());
+        let frames = field.wave_frames();
+        assert_eq!(
+            frames,
+            vec![".@.\n@@@\n.@.", "...\n.@.\n...", "...\n...\n..."]
+        );
+
+        let data = read_data(field.day(), "test1").unwrap();
+        field.fill(&data);
+        let waves = field.remove_waves();
+        assert_eq!(waves[0].len(), 13);
+        assert_eq!(waves.iter().map(|w| w.len()).sum::<usize>(), 43);
+    }
+
+    #[test]
+    fn fourth_test_neighborhoods_match_slow() {
+        let mut rng = Rng(0xa0761d6478bd642f);
+        for case in 0..300 {
+            let neighborhood = match case % 4 {
+                0 => Neighborhood::VonNeumann,
+                1 => Neighborhood::Moore,
+                2 => Neighborhood::Radius(1 + rng.next(2) as u32),
+                _ => Neighborhood::Custom(
+                    (0..1 + rng.next(5))
+                        .map(|_| (rng.next(5) as i32 - 2, rng.next(5) as i32 - 2))
+                        .collect(),
+                ),
+            };
+            let threshold = rng.next(6) as u32;
+            let wrap = rng.next(2) == 0;
+            let data = random_field(&mut rng, case % 100);
+
+            let make = || {
+                let mut field = Field::new()
+                    .with_neighborhood(neighborhood.clone())
+                    .unwrap()
+                    .with_threshold(threshold)
+                    .with_wrap(wrap);
+                field.fill(&data);
+                field
+            };
+            let (mut fast, mut slow, mut waves) = (make(), make(), make());
+            let total = slow.remove_all_slow();
+            assert_eq!(
+                fast.remove_all(),
+                total,
+                "{neighborhood:?} {threshold} {wrap}"
+            );
+            assert_eq!(fast.data, slow.data);
+            let waves = waves.remove_waves();
+            assert_eq!(waves.iter().map(|w| w.len() as u64).sum::<u64>(), total);
+            let check = make();
+            assert_eq!(check.accessible(), check.accessible_slow());
+        }
+    }
+
+    #[test]
+    fn fourth_test_layouts() {
+        let data = read_data(4, "test2").unwrap();
+
+        // every roll of 3x3 torus has all 8 neighbors
+        let mut field = Field::new().with_wrap(true);
+        field.fill(&data);
+        assert_eq!(field.accessible(), 0);
+        assert_eq!(field.remove_all(), 0);
+
+        // corners have 2 orthogonal neighbors, edges 3, center 4
+        let mut field = Field::new()
+            .with_neighborhood(Neighborhood::VonNeumann)
+            .unwrap()
+            .with_threshold(3);
+        field.fill(&data);
+        assert_eq!(field.accessible(), 4);
+        assert_eq!(field.remove_all(), 9);
+
+        assert_eq!(
+            "custom:-1,0; 0,2".parse::<Neighborhood>().unwrap(),
+            Neighborhood::Custom(vec![(-1, 0), (0, 2)])
+        );
+        assert_eq!(
+            "radius:2".parse::<Neighborhood>().unwrap().offsets().len(),
+            24
+        );
+        assert!("hex".parse::<Neighborhood>().is_err());
+
+        // neighbor amounts must fit in u16
+        assert!(
+            Field::new()
+                .with_neighborhood(Neighborhood::Radius(127))
+                .is_ok()
+        );
+        assert!(
+            Field::new()
+                .with_neighborhood(Neighborhood::Radius(128))
+                .is_err()
+        );
+        assert!(
+            Field::new()
+                .with_neighborhood(Neighborhood::Radius(u32::MAX))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn fourth_test_bit_grid_matches_slow() {
+        let mut rng = Rng(0x1b873593cc9e2d51);
+        for case in 0..40 {
+            let (h, w) = (1 + rng.next(150) as usize, 1 + rng.next(200) as usize);
+            let density = case * 5 % 100;
+            let data: Vec<String> = (0..h)
+                .map(|_| {
+                    (0..w)
+                        .map(|_| if rng.next(100) < density { '@' } else { '.' })
+                        .collect()
+                })
+                .collect();
+            let data = data.join("\n");
+
+            for wrap in [false, true] {
+                let mut field = Field::new()
+                    .with_neighborhood(Neighborhood::Radius(1 + case as u32 % 3))
+                    .unwrap()
+                    .with_threshold(rng.next(12) as u32)
+                    .with_wrap(wrap);
+                field.fill(&data);
+                assert_eq!(field.render(), data);
+                assert_eq!(field.accessible(), field.accessible_slow());
+
+                let counts = field.neighbor_counts();
+                for i in 0..h {
+                    for j in 0..w {
+                        assert_eq!(
+                            counts[i * w + j] as u32,
+                            field.check_adj(j as i32, i as i32)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fourth_test_bit_grid_ragged() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for _ in 0..100 {
+            let h = 1 + rng.next(20) as usize;
+            let lines: Vec<String> = (0..h)
+                .map(|_| {
+                    let w = rng.next(300) as usize;
+                    (0..w)
+                        .map(|_| ['@', '.', 'x'][rng.next(3) as usize])
+                        .collect()
+                })
+                .collect();
+            let sep = ["\n", "\r\n"][rng.next(2) as usize];
+            let mut data = lines.join(sep);
+            if rng.next(2) == 0 {
+                data.push_str(sep);
+            }
+            let grid = BitGrid::from_lines(&data, b'@');
+            let width = lines.iter().map(|l| l.len()).max().unwrap();
+            assert_eq!((grid.height(), grid.width()), (h, width));
+            for (i, line) in data.lines().enumerate() {
+                for j in 0..width {
+                    assert_eq!(grid.get(i, j), line.as_bytes().get(j) == Some(&b'@'));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fourth_test_clusters() {
+        let mut field = Field::new();
+        field.fill("@@...@\n@..@.@\n..@...\n@@@@@@\n@@@@@@\n@@@@@@\n@@@@@@");
+
+        let orthogonal = field.clusters(false);
+        let sizes: Vec<_> = orthogonal.clusters.iter().map(|c| c.size).collect();
+        assert_eq!(sizes, vec![3, 2, 1, 25]);
+        assert_eq!(orthogonal.clusters[3].bounds, (2, 0, 6, 5));
+        assert_eq!(
+            orthogonal.render(),
+            "AA...B\nA..C.B\n..D...\nDDDDDD\nDDDDDD\nDDDDDD\nDDDDDD"
+        );
+        let vanishes: Vec<_> = orthogonal.clusters.iter().map(|c| c.vanishes).collect();
+        assert_eq!(vanishes, vec![true, true, true, false]);
+        assert_eq!(orthogonal.clusters[0].accessible, 3);
+
+        let diagonal = field.clusters(true);
+        let sizes: Vec<_> = diagonal.clusters.iter().map(|c| c.size).collect();
+        assert_eq!(sizes, vec![3, 2, 26]);
+        assert_eq!(
+            diagonal
+                .clusters
+                .iter()
+                .map(|c| c.accessible)
+                .sum::<usize>() as u64,
+            field.accessible()
+        );
+        assert!(diagonal.to_string().ends_with("clusters 3, vanishing 2"));
+    }
 }
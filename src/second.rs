@@ -1,26 +1,279 @@
-use crate::utils::Exercise;
+use std::{fmt, thread};
 
-pub struct Product;
+use anyhow::{Result, anyhow};
+
+use crate::utils::{self, Exercise};
+
+/// Invalid ID classified by its minimal repeating block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidId {
+    pub id: u64,
+    /// Shortest block which gives the ID when repeated.
+    pub block: u64,
+    pub repeats: u32,
+}
+
+/// Invalid IDs found in one input range.
+pub struct RangeReport {
+    pub start: u64,
+    pub end: u64,
+    pub count: u128,
+    pub sum: u128,
+    /// Empty when there are too many IDs to list.
+    pub ids: Vec<InvalidId>,
+}
+
+pub struct Report {
+    pub radix: u32,
+    pub ranges: Vec<RangeReport>,
+    /// Pairs of input ranges sharing some IDs, which are summed twice.
+    pub overlaps: Vec<((u64, u64), (u64, u64))>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let num = |i: u64| Product::to_radix_string(i, self.radix);
+
+        for r in self.ranges.iter() {
+            writeln!(
+                f,
+                "{}-{}\tcount {}\tsum {}",
+                num(r.start),
+                num(r.end),
+                r.count,
+                r.sum
+            )?;
+            if r.ids.len() as u128 != r.count {
+                writeln!(f, "\t(too many to list)")?;
+            }
+            for inv in r.ids.iter() {
+                writeln!(f, "\t{}\t{} x {}", num(inv.id), num(inv.block), inv.repeats)?;
+            }
+        }
+
+        for (a, b) in self.overlaps.iter() {
+            writeln!(
+                f,
+                "warning: ranges {}-{} and {}-{} overlap",
+                num(a.0),
+                num(a.1),
+                num(b.0),
+                num(b.1)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Definition of an invalid product ID.
+pub trait IdRule: Sync {
+    /// Name used to pick the rule, see `rule_by_name`.
+    fn name(&self) -> String;
+
+    /// Checks `id` written in `radix`.
+    fn is_invalid(&self, id: u64, radix: u32) -> bool;
+
+    /// Whether validity depends only on the minimal repeating block,
+    /// which allows arithmetic sums instead of checking every ID.
+    fn by_period(&self) -> bool {
+        false
+    }
+
+    /// Whether `len`-digit IDs with minimal period `p` are invalid.
+    ///
+    /// Used only when `by_period` returns `true`.
+    fn accepts_period(&self, _len: u32, _p: u32) -> bool {
+        false
+    }
+}
+
+/// Some block repeated exactly twice (part 1).
+pub struct ExactlyTwice;
+
+impl IdRule for ExactlyTwice {
+    fn name(&self) -> String {
+        "twice".to_string()
+    }
+
+    fn is_invalid(&self, id: u64, radix: u32) -> bool {
+        Product::is_invalid(id, radix)
+    }
+
+    fn by_period(&self) -> bool {
+        true
+    }
+
+    fn accepts_period(&self, len: u32, p: u32) -> bool {
+        len.is_multiple_of(2) && (len / 2).is_multiple_of(p)
+    }
+}
+
+/// Some block repeated at least twice (part 2).
+pub struct AtLeastTwice;
+
+impl IdRule for AtLeastTwice {
+    fn name(&self) -> String {
+        "at-least-twice".to_string()
+    }
+
+    fn is_invalid(&self, id: u64, radix: u32) -> bool {
+        Product::is_invalid_certainly(id, radix)
+    }
+
+    fn by_period(&self) -> bool {
+        true
+    }
+
+    fn accepts_period(&self, len: u32, p: u32) -> bool {
+        p < len
+    }
+}
+
+/// Digits read the same in both directions.
+pub struct Palindrome;
+
+impl IdRule for Palindrome {
+    fn name(&self) -> String {
+        "palindrome".to_string()
+    }
+
+    fn is_invalid(&self, id: u64, radix: u32) -> bool {
+        let digs = Product::digits(id, radix);
+        digs.iter().eq(digs.iter().rev())
+    }
+}
+
+/// Some block repeated from `min` to `max` times inclusive.
+pub struct RepeatCount {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl IdRule for RepeatCount {
+    fn name(&self) -> String {
+        if self.max == u32::MAX {
+            format!("repeats:{}-", self.min)
+        } else {
+            format!("repeats:{}-{}", self.min, self.max)
+        }
+    }
+
+    fn is_invalid(&self, id: u64, radix: u32) -> bool {
+        // blocks start with a nonzero digit (see `block_range`), so 0 repeats nothing
+        if id == 0 {
+            return false;
+        }
+        let digs = Product::digits(id, radix);
+        let p = Product::minimal_period(&digs) as u32;
+        self.accepts_period(digs.len() as u32, p)
+    }
+
+    fn by_period(&self) -> bool {
+        true
+    }
+
+    fn accepts_period(&self, len: u32, p: u32) -> bool {
+        // a block of minimal period `p` can be taken `r` times for every `r | len / p`
+        let most = len / p;
+        (self.min.max(1)..=self.max.min(most)).any(|r| most.is_multiple_of(r))
+    }
+}
+
+/// Picks built-in rule: `twice`, `at-least-twice`, `palindrome`
+/// or `repeats:MIN-MAX` (`MAX` may be omitted).
+pub fn rule_by_name(name: &str) -> Result<Box<dyn IdRule>> {
+    match name {
+        "twice" => Ok(Box::new(ExactlyTwice)),
+        "at-least-twice" => Ok(Box::new(AtLeastTwice)),
+        "palindrome" => Ok(Box::new(Palindrome)),
+        _ => {
+            let (min, max) = name
+                .strip_prefix("repeats:")
+                .and_then(|r| r.split_once('-'))
+                .ok_or(anyhow!("Unknown rule {name}"))?;
+            let min = min.parse()?;
+            let max = if max.is_empty() {
+                u32::MAX
+            } else {
+                max.parse()?
+            };
+            if min == 0 || min > max {
+                return Err(anyhow!(
+                    "Repeat counts must satisfy 1 <= min <= max, got {min}-{max}"
+                ));
+            }
+            Ok(Box::new(RepeatCount { min, max }))
+        }
+    }
+}
+
+pub struct Product {
+    /// Radix of IDs in the input, in range [2, 36].
+    radix: u32,
+    /// Threads used by brute-force scans.
+    threads: usize,
+    /// Rule used by `sum` and `report`.
+    rule: Box<dyn IdRule>,
+}
 
 impl Product {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            radix: 10,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            rule: Box::new(AtLeastTwice),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: Box<dyn IdRule>) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub fn with_radix(mut self, radix: u32) -> Result<Self> {
+        if !(2..=36).contains(&radix) {
+            return Err(anyhow!("Radix must be in range [2, 36], got {radix}"));
+        }
+        self.radix = radix;
+        Ok(self)
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Digits of `i` in `radix`, most significant first.
+    fn digits(mut i: u64, radix: u32) -> Vec<u8> {
+        let mut digs = Vec::new();
+        loop {
+            digs.push((i % radix as u64) as u8);
+            i /= radix as u64;
+            if i == 0 {
+                break;
+            }
+        }
+        digs.reverse();
+        digs
     }
 
-    fn is_invalid(i: u64) -> bool {
-        let i_str = i.to_string();
-        let l = i_str.len();
+    /// Reference predicate: some block repeated exactly twice.
+    pub fn is_invalid(i: u64, radix: u32) -> bool {
+        let digs = Product::digits(i, radix);
+        let l = digs.len();
         if l % 2 != 0 {
             return false;
         }
-        i_str[..(l / 2)] == i_str[(l / 2)..]
+        digs[..(l / 2)] == digs[(l / 2)..]
     }
 
-    fn is_invalid_certainly(i: u64) -> bool {
-        let i_str = i.to_string();
-        let l = i_str.len();
+    /// Reference predicate: some block repeated at least twice.
+    pub fn is_invalid_certainly(i: u64, radix: u32) -> bool {
+        let digs = Product::digits(i, radix);
+        let l = digs.len();
         for w_len in 1..=l / 2 {
-            let w: Vec<_> = i_str.as_bytes().chunks(w_len).collect();
+            let w: Vec<_> = digs.chunks(w_len).collect();
             let first = w[0];
             if w.iter().all(|&w| w == first) {
                 return true;
@@ -28,6 +281,309 @@ impl Product {
         }
         false
     }
+
+    fn to_radix_string(i: u64, radix: u32) -> String {
+        Product::digits(i, radix)
+            .into_iter()
+            .map(|d| char::from_digit(d as u32, radix).unwrap())
+            .collect()
+    }
+
+    fn digit_len(i: u64, radix: u32) -> u32 {
+        i.checked_ilog(radix as u64).unwrap_or(0) + 1
+    }
+
+    /// Minimal period of `digs`: the shortest block length dividing
+    /// `digs.len()` whose repetition gives back `digs`.
+    fn minimal_period(digs: &[u8]) -> usize {
+        let l = digs.len();
+        (1..=l)
+            .find(|&d| l.is_multiple_of(d) && (d..l).all(|i| digs[i] == digs[i - d]))
+            .unwrap_or(l)
+    }
+
+    /// `1 + radix^period + radix^(2 * period) + ...` with `len / period` terms.
+    ///
+    /// Multiplying a `period`-digit block by it repeats the block.
+    fn repeat_mult(radix: u32, len: u32, period: u32) -> u128 {
+        let step = (radix as u128).pow(period);
+        (0..len / period).fold(0, |acc, _| acc * step + 1)
+    }
+
+    /// Range of `period`-digit blocks whose repetition up to `len` digits
+    /// falls into `start..=end`, or `None` if there are none.
+    fn block_range(
+        start: u64,
+        end: u64,
+        radix: u32,
+        len: u32,
+        period: u32,
+    ) -> Option<(u128, u128)> {
+        let mult = Product::repeat_mult(radix, len, period);
+        let radix = radix as u128;
+
+        let lo = (start as u128).max(radix.pow(len - 1));
+        let hi = (end as u128).min(radix.pow(len) - 1);
+        if lo > hi {
+            return None;
+        }
+
+        let first = lo.div_ceil(mult);
+        let last = hi / mult;
+        (first <= last).then_some((first, last))
+    }
+
+    /// Count and sum of all `len`-digit numbers in `start..=end` built from
+    /// a `period`-digit block repeated `len / period` times.
+    ///
+    /// Such numbers are `block * repeat_mult`, so the blocks inside
+    /// the range form an arithmetic progression.
+    fn stats_with_period(start: u64, end: u64, radix: u32, len: u32, period: u32) -> (u128, u128) {
+        match Product::block_range(start, end, radix, len, period) {
+            Some((first, last)) => {
+                let count = last - first + 1;
+                let mult = Product::repeat_mult(radix, len, period);
+                (count, (first + last) * count / 2 * mult)
+            }
+            None => (0, 0),
+        }
+    }
+
+    /// Count and sum of numbers in `start..=end` whose minimal period `p`
+    /// (for a number of `len` digits in `radix`) satisfies `accept(len, p)`.
+    ///
+    /// O(log(end)^2)
+    ///
+    /// A number with period `d` also has every period that is a multiple of
+    /// its minimal one, so stats for exact minimal periods are obtained
+    /// by subtracting smaller divisors (inclusion-exclusion).
+    fn stats_by_period<F>(start: u64, end: u64, radix: u32, accept: F) -> (u128, u128)
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        let (mut count, mut total) = (0, 0);
+        for len in Product::digit_len(start, radix)..=Product::digit_len(end, radix) {
+            let divisors: Vec<u32> = (1..=len).filter(|d| len % d == 0).collect();
+            let mut exact: Vec<(u128, u128)> = Vec::with_capacity(divisors.len());
+            for (idx, &d) in divisors.iter().enumerate() {
+                let (mut c, mut s) = Product::stats_with_period(start, end, radix, len, d);
+                for (e, (ec, es)) in divisors[..idx].iter().zip(&exact) {
+                    if d % e == 0 {
+                        c -= ec;
+                        s -= es;
+                    }
+                }
+                exact.push((c, s));
+                if accept(len, d) {
+                    count += c;
+                    total += s;
+                }
+            }
+        }
+        (count, total)
+    }
+
+    /// All numbers in `start..=end` whose minimal period satisfies
+    /// `accept(len, p)`, in ascending order.
+    ///
+    /// Visits every repeated block of an accepted period, so it should be
+    /// called only when `stats_by_period` reports a small count.
+    fn ids_by_period<F>(start: u64, end: u64, radix: u32, accept: F) -> Vec<InvalidId>
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        let mut ids = Vec::new();
+        for len in Product::digit_len(start, radix)..=Product::digit_len(end, radix) {
+            for p in (1..=len).filter(|d| len % d == 0 && accept(len, *d)) {
+                let Some((first, last)) = Product::block_range(start, end, radix, len, p) else {
+                    continue;
+                };
+                let mult = Product::repeat_mult(radix, len, p);
+                for block in first..=last {
+                    let digs = Product::digits(block as u64, radix);
+                    // blocks that repeat themselves belong to a smaller period
+                    if Product::minimal_period(&digs) == digs.len() {
+                        ids.push(InvalidId {
+                            id: (block * mult) as u64,
+                            block: block as u64,
+                            repeats: len / p,
+                        });
+                    }
+                }
+            }
+        }
+        ids.sort_by_key(|inv| inv.id);
+        ids
+    }
+
+    /// Sum of numbers in `start..=end` made of a block repeated exactly twice.
+    pub fn sum_invalid(start: u64, end: u64, radix: u32) -> u128 {
+        Product::stats_by_period(start, end, radix, |l, p| ExactlyTwice.accepts_period(l, p)).1
+    }
+
+    /// Sum of numbers in `start..=end` made of a block repeated at least twice.
+    pub fn sum_invalid_certainly(start: u64, end: u64, radix: u32) -> u128 {
+        Product::stats_by_period(start, end, radix, |l, p| AtLeastTwice.accepts_period(l, p)).1
+    }
+
+    /// Count and sum of IDs in `start..=end` invalid by `rule`, with the IDs
+    /// in ascending order when there are at most `limit` of them.
+    ///
+    /// Rules which check every ID find all three in one parallel pass.
+    fn range_stats(
+        &self,
+        start: u64,
+        end: u64,
+        rule: &dyn IdRule,
+        limit: usize,
+    ) -> (u128, u128, Option<Vec<InvalidId>>) {
+        if rule.by_period() {
+            let (count, sum) =
+                Product::stats_by_period(start, end, self.radix, |l, p| rule.accepts_period(l, p));
+            let ids = (count <= limit as u128).then(|| {
+                Product::ids_by_period(start, end, self.radix, |l, p| rule.accepts_period(l, p))
+            });
+            return (count, sum, ids);
+        }
+
+        let radix = self.radix;
+        let (count, sum, ids) = utils::parallel_range_fold(
+            &[start..=end],
+            self.threads,
+            || (0u128, 0u128, Some(Vec::new())),
+            |(count, sum, mut ids), i| {
+                if !rule.is_invalid(i, radix) {
+                    return (count, sum, ids);
+                }
+                if let Some(list) = ids.as_mut() {
+                    list.push(i);
+                    if list.len() > limit {
+                        ids = None;
+                    }
+                }
+                (count + 1, sum + i as u128, ids)
+            },
+            |a, b| {
+                let ids = match (a.2, b.2) {
+                    (Some(mut x), Some(y)) if x.len() + y.len() <= limit => {
+                        x.extend(y);
+                        Some(x)
+                    }
+                    _ => None,
+                };
+                (a.0 + b.0, a.1 + b.1, ids)
+            },
+        );
+        let ids = ids.map(|ids| ids.into_iter().map(|id| self.classify(id)).collect());
+        (count, sum, ids)
+    }
+
+    /// Invalid ID with its minimal repeating block.
+    fn classify(&self, id: u64) -> InvalidId {
+        let digs = Product::digits(id, self.radix);
+        let p = Product::minimal_period(&digs);
+        let block = digs[..p]
+            .iter()
+            .fold(0, |acc, &d| acc * self.radix as u64 + d as u64);
+        InvalidId {
+            id,
+            block,
+            repeats: (digs.len() / p) as u32,
+        }
+    }
+
+    /// Builds per range report of IDs invalid by `self.rule`.
+    ///
+    /// IDs are listed only for ranges with at most `limit` of them,
+    /// otherwise only their count and sum are reported.
+    pub fn report(&self, data: &str, limit: usize) -> Report {
+        let ranges = self.parse_ranges(data);
+
+        let mut reports = Vec::new();
+        for &(start, end) in ranges.iter() {
+            let (count, sum, ids) = self.range_stats(start, end, self.rule.as_ref(), limit);
+            let ids = ids.unwrap_or_default();
+            reports.push(RangeReport {
+                start,
+                end,
+                count,
+                sum,
+                ids,
+            });
+        }
+
+        let mut overlaps = Vec::new();
+        for (i, a) in ranges.iter().enumerate() {
+            for b in ranges.iter().skip(i + 1) {
+                if a.0 <= b.1 && b.0 <= a.1 {
+                    overlaps.push((*a, *b));
+                }
+            }
+        }
+
+        Report {
+            radix: self.radix,
+            ranges: reports,
+            overlaps,
+        }
+    }
+
+    /// Sum of IDs invalid by `rule` in all ranges of `data`.
+    ///
+    /// Rules defined by repeating blocks are summed arithmetically,
+    /// others check every ID on `self.threads` threads.
+    pub fn sum_with_rule(&self, data: &str, rule: &dyn IdRule) -> u128 {
+        if rule.by_period() {
+            return self
+                .parse_ranges(data)
+                .into_iter()
+                .map(|(start, end)| {
+                    Product::stats_by_period(start, end, self.radix, |l, p| {
+                        rule.accepts_period(l, p)
+                    })
+                    .1
+                })
+                .sum();
+        }
+        self.scan(data, rule)
+    }
+
+    /// Sum of IDs invalid by `self.rule` in all ranges of `data`.
+    pub fn sum(&self, data: &str) -> u128 {
+        self.sum_with_rule(data, self.rule.as_ref())
+    }
+
+    /// Sum of IDs invalid by `rule` in all ranges of `data`.
+    ///
+    /// Checks every ID, but splits the work between `self.threads` threads.
+    pub fn scan(&self, data: &str, rule: &dyn IdRule) -> u128 {
+        let ranges: Vec<_> = self
+            .parse_ranges(data)
+            .into_iter()
+            .map(|(start, end)| start..=end)
+            .collect();
+        utils::parallel_range_sum(&ranges, self.threads, |i| {
+            if rule.is_invalid(i, self.radix) {
+                i as u128
+            } else {
+                0
+            }
+        })
+    }
+
+    /// Parses comma separated `start-end` ranges written in `self.radix`.
+    fn parse_ranges(&self, data: &str) -> Vec<(u64, u64)> {
+        data.trim()
+            .split(",")
+            .map(|s| {
+                let r: Vec<u64> = s
+                    .split("-")
+                    .map(|n| u64::from_str_radix(n, self.radix).unwrap())
+                    .collect();
+                (r[0], r[1])
+            })
+            .collect()
+    }
 }
 
 impl Exercise for Product {
@@ -36,29 +592,11 @@ impl Exercise for Product {
     }
 
     fn part1(&mut self, data: &str) -> String {
-        let ranges = data.trim().split(",").map(|s| {
-            let r: Vec<u64> = s.split("-").map(|n| n.parse::<u64>().unwrap()).collect();
-            let (start, end) = (r[0], r[1]);
-            start..=end
-        });
-        let mut total_invalid: u64 = 0;
-        for r in ranges {
-            total_invalid += r.filter(|&i| Product::is_invalid(i)).sum::<u64>();
-        }
-        total_invalid.to_string()
+        self.sum_with_rule(data, &ExactlyTwice).to_string()
     }
 
     fn part2(&mut self, data: &str) -> String {
-        let ranges = data.trim().split(",").map(|s| {
-            let r: Vec<u64> = s.split("-").map(|n| n.parse::<u64>().unwrap()).collect();
-            let (start, end) = (r[0], r[1]);
-            start..=end
-        });
-        let mut total_invalid: u64 = 0;
-        for r in ranges {
-            total_invalid += r.filter(|&i| Product::is_invalid_certainly(i)).sum::<u64>();
-        }
-        total_invalid.to_string()
+        self.sum_with_rule(data, &AtLeastTwice).to_string()
     }
 }
 
@@ -84,10 +622,230 @@ mod tests {
         assert_eq!(res_part1, "4174379265");
     }
 
+    #[test]
+    fn second_test_parallel_scan() {
+        let data = utils::read_data(2, "test1").unwrap();
+        for threads in [1, 2, 3, 8] {
+            let prod = Product::new().with_threads(threads);
+            assert_eq!(prod.scan(&data, &ExactlyTwice), 1227775554);
+            assert_eq!(prod.scan(&data, &AtLeastTwice), 4174379265);
+        }
+    }
+
     #[test]
     fn second_test_certainty_function_true() {
         for case in [444, 565656, 123123] {
-            assert!(Product::is_invalid_certainly(case))
+            assert!(Product::is_invalid_certainly(case, 10))
+        }
+    }
+
+    #[test]
+    fn second_test_arithmetic_matches_brute_force() {
+        let ranges = [
+            (0, 0),
+            (1, 9),
+            (0, 100_000),
+            (95, 115),
+            (998, 1012),
+            (222_220, 222_224),
+            (1_188_511_880, 1_188_511_890),
+            (9_999_990, 10_001_000),
+        ];
+        for (start, end) in ranges {
+            let twice: u128 = (start..=end)
+                .filter(|&i| Product::is_invalid(i, 10))
+                .map(u128::from)
+                .sum();
+            let certainly: u128 = (start..=end)
+                .filter(|&i| Product::is_invalid_certainly(i, 10))
+                .map(u128::from)
+                .sum();
+            assert_eq!(Product::sum_invalid(start, end, 10), twice);
+            assert_eq!(Product::sum_invalid_certainly(start, end, 10), certainly);
+        }
+    }
+
+    #[test]
+    fn second_test_full_u64_range() {
+        let top = u64::MAX - 200_000;
+        let brute: u128 = (top..=u64::MAX)
+            .filter(|&i| Product::is_invalid_certainly(i, 10))
+            .map(u128::from)
+            .sum();
+        assert_eq!(Product::sum_invalid_certainly(top, u64::MAX, 10), brute);
+
+        let mid = 12_345_678_901;
+        for sum in [Product::sum_invalid, Product::sum_invalid_certainly] {
+            for radix in [2, 10, 36] {
+                assert_eq!(
+                    sum(0, u64::MAX, radix),
+                    sum(0, mid, radix) + sum(mid + 1, u64::MAX, radix)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn second_test_radix_predicates() {
+        assert!(Product::is_invalid(0xabab, 16));
+        assert!(!Product::is_invalid(0xabab, 10));
+        assert!(Product::is_invalid_certainly(0b101010, 2));
+        assert!(!Product::is_invalid(0b101010, 2));
+        assert!(Product::is_invalid_certainly(
+            u64::from_str_radix("zzz", 36).unwrap(),
+            36
+        ));
+    }
+
+    #[test]
+    fn second_test_radix_sums_match_brute_force() {
+        for radix in [2, 3, 7, 16, 36] {
+            for (start, end) in [(0, 70_000), (1_295, 50_000), (123_456, 160_000)] {
+                let twice: u128 = (start..=end)
+                    .filter(|&i| Product::is_invalid(i, radix))
+                    .map(u128::from)
+                    .sum();
+                let certainly: u128 = (start..=end)
+                    .filter(|&i| Product::is_invalid_certainly(i, radix))
+                    .map(u128::from)
+                    .sum();
+                assert_eq!(Product::sum_invalid(start, end, radix), twice);
+                assert_eq!(Product::sum_invalid_certainly(start, end, radix), certainly);
+            }
+        }
+    }
+
+    #[test]
+    fn second_test_report() {
+        let prod = Product::new();
+        let report = prod.report("95-115,110-1012,1-1000000", 5);
+
+        let r = &report.ranges[0];
+        assert_eq!(r.count, 2);
+        assert_eq!(
+            r.ids,
+            vec![
+                InvalidId {
+                    id: 99,
+                    block: 9,
+                    repeats: 2
+                },
+                InvalidId {
+                    id: 111,
+                    block: 1,
+                    repeats: 3
+                }
+            ]
+        );
+
+        let r = &report.ranges[1];
+        assert_eq!(r.ids.len(), 0);
+        assert_eq!(r.count, 9 + 1);
+
+        let r = &report.ranges[2];
+        let brute: Vec<u64> = (1..=1_000_000)
+            .filter(|&i| Product::is_invalid_certainly(i, 10))
+            .collect();
+        assert_eq!(r.count, brute.len() as u128);
+        assert_eq!(r.sum, brute.iter().map(|&i| i as u128).sum());
+
+        assert_eq!(report.overlaps.len(), 3);
+        assert!(
+            report
+                .to_string()
+                .contains("warning: ranges 95-115 and 110-1012 overlap")
+        );
+    }
+
+    #[test]
+    fn second_test_ids_match_brute_force() {
+        for radix in [2, 10, 16] {
+            let ids =
+                Product::ids_by_period(0, 100_000, radix, |l, p| AtLeastTwice.accepts_period(l, p));
+            let brute: Vec<u64> = (0..=100_000)
+                .filter(|&i| Product::is_invalid_certainly(i, radix))
+                .collect();
+            assert_eq!(ids.iter().map(|inv| inv.id).collect::<Vec<_>>(), brute);
+            for inv in ids {
+                let block = Product::digits(inv.block, radix);
+                assert_eq!(Product::minimal_period(&block), block.len());
+                assert_eq!(
+                    block.repeat(inv.repeats as usize),
+                    Product::digits(inv.id, radix)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn second_test_rules() {
+        let data = "0-200000";
+        let prod = Product::new().with_threads(4);
+        for name in [
+            "twice",
+            "at-least-twice",
+            "palindrome",
+            "repeats:3-",
+            "repeats:2-3",
+            "repeats:4-4",
+            "repeats:1-1",
+        ] {
+            let rule = rule_by_name(name).unwrap();
+            assert_eq!(rule.name(), name);
+            let invalid: Vec<u64> = (0..=200_000).filter(|&i| rule.is_invalid(i, 10)).collect();
+            let brute: u128 = invalid.iter().map(|&i| i as u128).sum();
+            assert_eq!(prod.sum_with_rule(data, rule.as_ref()), brute);
+            assert_eq!(prod.scan(data, rule.as_ref()), brute);
+
+            let report = Product::new().with_rule(rule).report(data, 0);
+            assert_eq!(report.ranges[0].count, invalid.len() as u128, "{name}");
+        }
+
+        assert!(rule_by_name("repeats").is_err());
+        assert!(rule_by_name("repeats:0-2").is_err());
+        assert!(rule_by_name("repeats:3-2").is_err());
+        assert!(!RepeatCount { min: 1, max: 1 }.is_invalid(0, 10));
+        assert!(RepeatCount { min: 4, max: 4 }.is_invalid(1111, 10));
+        assert!(RepeatCount { min: 2, max: 2 }.is_invalid(1111, 10));
+        assert!(!RepeatCount { min: 3, max: 3 }.is_invalid(1111, 10));
+        assert!(Palindrome.is_invalid(0xabcba, 16));
+    }
+
+    #[test]
+    fn second_test_palindrome_report() {
+        let prod = Product::new().with_rule(Box::new(Palindrome));
+        let report = prod.report("100-131", 10);
+        let ids: Vec<_> = report.ranges[0].ids.iter().map(|inv| inv.id).collect();
+        assert_eq!(ids, vec![101, 111, 121, 131]);
+        assert_eq!(report.ranges[0].sum, 464);
+        assert_eq!(report.ranges[0].ids[1].block, 1);
+
+        // IDs collected on several threads keep their order and the limit
+        let expected: Vec<u64> = (1000..=9999)
+            .filter(|&i| Palindrome.is_invalid(i, 10))
+            .collect();
+        for threads in [1, 3, 8] {
+            let prod = Product::new()
+                .with_rule(Box::new(Palindrome))
+                .with_threads(threads);
+            let report = prod.report("1000-9999", 90);
+            let ids: Vec<_> = report.ranges[0].ids.iter().map(|inv| inv.id).collect();
+            assert_eq!(ids, expected);
+            let report = prod.report("1000-9999", 89);
+            assert_eq!(report.ranges[0].count, 90);
+            assert!(report.ranges[0].ids.is_empty());
+        }
+    }
+
+    #[test]
+    fn second_test_hex_input() {
+        // 0x11, 0x22 ... 0xff and 0x1010
+        let mut prod = Product::new().with_radix(16).unwrap();
+        let expected: u64 = (1..=15).map(|d| d * 0x11).sum::<u64>() + 0x1010;
+        assert_eq!(prod.part1("a-1010"), expected.to_string());
+
+        for radix in [0, 1, 37, 40] {
+            assert!(Product::new().with_radix(radix).is_err());
         }
     }
 }
@@ -1,93 +1,297 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
+
+use num_bigint::BigUint;
 
 use crate::utils::Exercise;
 
+/// Quantum timelines by the place where they leave the manifold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timelines {
+    /// Timelines leaving through the bottom or a missing cell of a short row.
+    pub columns: Vec<BigUint>,
+    /// Timelines split past the left edge.
+    pub left: BigUint,
+    /// Timelines split past the right edge.
+    pub right: BigUint,
+}
+
+impl Timelines {
+    fn new(width: usize) -> Self {
+        Self {
+            columns: vec![BigUint::ZERO; width],
+            left: BigUint::ZERO,
+            right: BigUint::ZERO,
+        }
+    }
+
+    pub fn total(&self) -> BigUint {
+        self.columns.iter().sum::<BigUint>() + &self.left + &self.right
+    }
+
+    fn exit(&mut self, col: Option<usize>, count: &BigUint) {
+        match col {
+            None => self.left += count,
+            Some(j) if j >= self.columns.len() => self.right += count,
+            Some(j) => self.columns[j] += count,
+        }
+    }
+}
+
+/// Statistics of a beam from a single source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceStats {
+    pub row: usize,
+    pub col: usize,
+    /// Splitters hit by the classical beam.
+    pub splits: u64,
+    pub timelines: Timelines,
+    /// Quantum timelines stopped inside the manifold.
+    pub absorbed: BigUint,
+}
+
+/// Manifold cell. Every character without its own meaning, `^` and `S`
+/// included, is a two-way splitter.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Element {
+    /// `.`
+    Empty,
+    /// Splits a falling beam into the cells to its left and right.
+    Splitter,
+    /// `<` sends a falling beam only to the left cell.
+    LeftSplitter,
+    /// `>` sends a falling beam only to the right cell.
+    RightSplitter,
+    /// `/` turns a falling beam to the left and a beam moving left down.
+    MirrorLeft,
+    /// `\` turns a falling beam to the right and a beam moving right down.
+    MirrorRight,
+    /// `#` stops every beam.
+    Absorber,
+}
+
+impl Element {
+    fn from_byte(c: u8) -> Self {
+        match c {
+            b'.' => Element::Empty,
+            b'<' => Element::LeftSplitter,
+            b'>' => Element::RightSplitter,
+            b'/' => Element::MirrorLeft,
+            b'\\' => Element::MirrorRight,
+            b'#' => Element::Absorber,
+            _ => Element::Splitter,
+        }
+    }
+}
+
+/// Result of sweeping beams down the manifold.
+struct Sweep {
+    splits: u64,
+    timelines: Timelines,
+    absorbed: BigUint,
+}
+
 pub struct Tachyon {
-    field: HashMap<(usize, usize), char>,
-    beams: HashSet<(usize, usize)>,
+    /// Manifold rows, may be of different length.
+    grid: Vec<Vec<u8>>,
+    width: usize,
+    /// Positions of `S` in reading order.
+    sources: Vec<(usize, usize)>,
 }
 
 impl Tachyon {
     pub fn new() -> Self {
         Self {
-            field: HashMap::new(),
-            beams: HashSet::new(),
+            grid: Vec::new(),
+            width: 0,
+            sources: Vec::new(),
         }
     }
 
     fn clear(&mut self) {
-        self.field.clear();
-        self.beams.clear();
+        self.grid.clear();
+        self.width = 0;
+        self.sources.clear();
     }
 
     fn fill(&mut self, data: &str) {
         for (i, line) in data.lines().enumerate() {
-            for (j, c) in line.trim().chars().enumerate() {
-                self.field.insert((i, j), c);
-                if c == 'S' {
-                    self.beams.insert((i + 1, j));
+            let row = line.trim().as_bytes().to_vec();
+            for (j, &c) in row.iter().enumerate() {
+                if c == b'S' {
+                    self.sources.push((i, j));
                 }
             }
+            self.width = self.width.max(row.len());
+            self.grid.push(row);
         }
     }
 
-    /// Returns amount of splits during one step.
-    fn step(&mut self) -> u32 {
+    fn cell(&self, i: usize, j: usize) -> Option<Element> {
+        self.grid.get(i)?.get(j).copied().map(Element::from_byte)
+    }
+
+    /// Sweeps beams standing in `row` at `cols` down to the bottom, one row at
+    /// a time. A column holds the number of timelines falling into it, a beam
+    /// is present where the number is not zero.
+    ///
+    /// Splitter halves move one cell aside and fall from there, a side cell
+    /// which is not `.` absorbs its half. Mirrors send beams along the row
+    /// until they are turned down by another mirror, stopped or leave the
+    /// manifold. Beams never move up, so a beam hitting the back of a mirror
+    /// or the side of a splitter is absorbed. Beams leaving the manifold
+    /// through the bottom or the sides count as finished timelines.
+    fn sweep(&self, row: usize, cols: &[usize]) -> Sweep {
+        let mut counts = vec![BigUint::ZERO; self.width];
+        for &j in cols {
+            counts[j] += 1u32;
+        }
+        let mut next = vec![BigUint::ZERO; self.width];
+        // beams moving sideways by the column they enter
+        let mut left = vec![BigUint::ZERO; self.width];
+        let mut right = vec![BigUint::ZERO; self.width + 1];
+
         let mut splits = 0;
-        let next_positions: Vec<_> = self.beams.iter().map(|&(i, j)| (i + 1, j)).collect();
-        let mut new_beams = HashSet::new();
-        for (i, j) in next_positions {
-            if !self.field.contains_key(&(i, j)) {
-                continue;
-            }
+        let mut timelines = Timelines::new(self.width);
+        let mut absorbed = BigUint::ZERO;
+        for i in row + 1..self.grid.len() {
+            next.fill(BigUint::ZERO);
+            for j in 0..self.width {
+                if counts[j] == BigUint::ZERO {
+                    continue;
+                }
+                let count = &counts[j];
+                let (to_left, to_right) = match self.cell(i, j) {
+                    None => {
+                        timelines.exit(Some(j), count);
+                        continue;
+                    }
+                    Some(Element::Empty) => {
+                        next[j] += count;
+                        continue;
+                    }
+                    Some(Element::Absorber) => {
+                        absorbed += count;
+                        continue;
+                    }
+                    Some(Element::MirrorLeft) => {
+                        match j.checked_sub(1) {
+                            None => timelines.exit(None, count),
+                            Some(k) => left[k] += count,
+                        }
+                        continue;
+                    }
+                    Some(Element::MirrorRight) => {
+                        right[j + 1] += count;
+                        continue;
+                    }
+                    Some(Element::LeftSplitter) => (true, false),
+                    Some(Element::RightSplitter) => (false, true),
+                    Some(Element::Splitter) => (true, true),
+                };
 
-            if self.field[&(i, j)] == '.' {
-                new_beams.insert((i, j));
-            } else {
                 splits += 1;
-                let left = (i, j - 1);
-                let right = (i, j + 1);
-                if self.field.contains_key(&left) && self.field[&left] == '.' {
-                    new_beams.insert(left);
+                let sides = [
+                    to_left.then(|| j.checked_sub(1)),
+                    to_right.then_some(Some(j + 1)),
+                ];
+                for side in sides.into_iter().flatten() {
+                    match side.map(|k| (k, self.cell(i, k))) {
+                        None | Some((_, None)) => timelines.exit(side, count),
+                        Some((k, Some(Element::Empty))) => next[k] += count,
+                        Some(_) => absorbed += count,
+                    }
+                }
+            }
+
+            // beams moving right, then left
+            let mut carry = BigUint::ZERO;
+            for k in 0..self.width {
+                carry += std::mem::take(&mut right[k]);
+                if carry == BigUint::ZERO {
+                    continue;
+                }
+                match self.cell(i, k) {
+                    None => timelines.exit(Some(k), &std::mem::take(&mut carry)),
+                    Some(Element::Empty) => (),
+                    Some(Element::MirrorRight) => next[k] += std::mem::take(&mut carry),
+                    Some(_) => absorbed += std::mem::take(&mut carry),
                 }
-                if self.field.contains_key(&right) && self.field[&right] == '.' {
-                    new_beams.insert(right);
+            }
+            carry += std::mem::take(&mut right[self.width]);
+            timelines.exit(Some(self.width), &carry);
+
+            let mut carry = BigUint::ZERO;
+            for k in (0..self.width).rev() {
+                carry += std::mem::take(&mut left[k]);
+                if carry == BigUint::ZERO {
+                    continue;
+                }
+                match self.cell(i, k) {
+                    None => timelines.exit(Some(k), &std::mem::take(&mut carry)),
+                    Some(Element::Empty) => (),
+                    Some(Element::MirrorLeft) => next[k] += std::mem::take(&mut carry),
+                    Some(_) => absorbed += std::mem::take(&mut carry),
                 }
             }
+            timelines.exit(None, &carry);
+
+            std::mem::swap(&mut counts, &mut next);
         }
-        self.beams = new_beams;
-        splits
-    }
-
-    fn inner_timeline(
-        &mut self,
-        i: usize,
-        j: usize,
-        mem: &mut HashMap<(usize, usize), u64>,
-    ) -> u64 {
-        if let Some(&val) = mem.get(&(i, j)) {
-            return val;
+        for (j, count) in counts.iter().enumerate() {
+            timelines.exit(Some(j), count);
         }
 
-        if !self.field.contains_key(&(i, j)) {
-            mem.insert((i, j), 1);
-            return 1;
+        Sweep {
+            splits,
+            timelines,
+            absorbed,
         }
+    }
 
-        let result = if self.field[&(i, j)] == '.' {
-            self.inner_timeline(i + 1, j, mem)
-        } else {
-            self.inner_timeline(i, j - 1, mem) + self.inner_timeline(i, j + 1, mem)
-        };
+    /// Splits of all classical beams. Like every sweep, a beam first enters
+    /// the cell directly below its source. Beams of sources in different rows
+    /// never merge.
+    fn splits(&self) -> u64 {
+        let mut by_row: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for &(i, j) in self.sources.iter() {
+            by_row.entry(i).or_default().push(j);
+        }
+        by_row
+            .iter()
+            .map(|(&i, cols)| self.sweep(i, cols).splits)
+            .sum()
+    }
 
-        mem.insert((i, j), result);
-        result
+    /// Timelines of a quantum beam from the first source.
+    fn quantum_timelines(&self) -> Timelines {
+        let (i, j) = self.sources[0];
+        self.sweep(i, &[j]).timelines
     }
 
-    fn quantum_timelines(&mut self) -> u64 {
-        let beam = self.beams.iter().next().unwrap();
-        let mut mem = HashMap::new();
-        return self.inner_timeline(beam.0, beam.1, &mut mem);
+    /// Reads manifold from `data` and sweeps every source on its own.
+    pub fn source_stats(&mut self, data: &str) -> Vec<SourceStats> {
+        self.clear();
+        self.fill(data);
+        self.sources
+            .iter()
+            .map(|&(row, col)| {
+                let sweep = self.sweep(row, &[col]);
+                SourceStats {
+                    row,
+                    col,
+                    splits: sweep.splits,
+                    timelines: sweep.timelines,
+                    absorbed: sweep.absorbed,
+                }
+            })
+            .collect()
+    }
+
+    /// Reads manifold from `data` and counts quantum timelines by exit.
+    pub fn timelines(&mut self, data: &str) -> Timelines {
+        self.clear();
+        self.fill(data);
+        self.quantum_timelines()
     }
 }
 
@@ -99,27 +303,19 @@ impl Exercise for Tachyon {
     fn part1(&mut self, data: &str) -> String {
         self.clear();
         self.fill(data);
-
-        let mut splits = 0;
-        let mut cur_splits = self.step();
-        while !self.beams.is_empty() || cur_splits > 0 {
-            splits += cur_splits;
-            cur_splits = self.step();
-        }
-
-        splits.to_string()
+        self.splits().to_string()
     }
 
     fn part2(&mut self, data: &str) -> String {
         self.clear();
         self.fill(data);
-        self.quantum_timelines().to_string()
+        self.quantum_timelines().total().to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils;
+    use crate::utils::{self, Rng};
 
     use super::*;
 
@@ -138,4 +334,156 @@ mod tests {
         let res = tachyon.part2(&data);
         assert_eq!(res, "40");
     }
+
+    #[test]
+    fn seventh_test_tall() {
+        // deep enough to overflow the stack with recursion per cell
+        let mut data = String::from("..S..\n");
+        for i in 0..200_000 {
+            data.push_str(if i % 2 == 0 { ".....\n" } else { "..^..\n" });
+        }
+        let mut tachyon = Tachyon::new();
+        assert_eq!(tachyon.part1(&data), "1");
+        assert_eq!(tachyon.part2(&data), "2");
+    }
+
+    #[test]
+    fn seventh_test_big() {
+        // every beam hits a splitter on every level, timelines double
+        let levels = 80;
+        let width = 2 * levels + 3;
+        let center = levels + 1;
+        let mut data = format!("{}S{}\n", ".".repeat(center), ".".repeat(center + 1));
+        for level in 0..levels {
+            let row: String = (0..width)
+                .map(|j| {
+                    let off = j.abs_diff(center);
+                    match off <= level && off % 2 == level % 2 {
+                        true => '^',
+                        false => '.',
+                    }
+                })
+                .collect();
+            data.push_str(&format!("{}\n{row}\n", ".".repeat(width)));
+        }
+
+        let mut tachyon = Tachyon::new();
+        let timelines = tachyon.timelines(&data);
+        assert_eq!(timelines.total(), BigUint::from(1u32) << levels);
+        assert_eq!(timelines.left, BigUint::ZERO);
+        assert_eq!(timelines.right, BigUint::ZERO);
+        // binomial coefficients on every second column
+        let mut binom = BigUint::from(1u32);
+        for k in 0..=levels {
+            assert_eq!(timelines.columns[center - levels + 2 * k], binom);
+            binom = binom * (levels - k) / (k + 1);
+        }
+        assert_eq!(
+            tachyon.part2(&data),
+            (BigUint::from(1u32) << levels).to_string()
+        );
+    }
+
+    #[test]
+    fn seventh_test_exits() {
+        let data = ".S..\n.^..\n^...\n";
+        let timelines = Tachyon::new().timelines(data);
+        assert_eq!(timelines.left, BigUint::from(1u32));
+        assert_eq!(timelines.right, BigUint::ZERO);
+        assert_eq!(timelines.columns, [0u32, 1, 1, 0].map(BigUint::from));
+    }
+
+    /// Reference walking every timeline, returns exited and absorbed ones.
+    fn walk(tachyon: &Tachyon, i: usize, j: Option<usize>, dir: i8) -> (u64, u64) {
+        let Some(cell) = j.and_then(|j| tachyon.cell(i, j)) else {
+            return (1, 0);
+        };
+        let j = j.unwrap();
+        let down = |k: Option<usize>| match k.map(|k| (k, tachyon.cell(i, k))) {
+            None | Some((_, None)) => (1, 0),
+            Some((k, Some(Element::Empty))) => walk(tachyon, i + 1, Some(k), 0),
+            Some(_) => (0, 1),
+        };
+        let add = |a: (u64, u64), b: (u64, u64)| (a.0 + b.0, a.1 + b.1);
+        match (dir, cell) {
+            (0, Element::Empty) => walk(tachyon, i + 1, Some(j), 0),
+            (0, Element::MirrorLeft) => walk(tachyon, i, j.checked_sub(1), -1),
+            (0, Element::MirrorRight) => walk(tachyon, i, Some(j + 1), 1),
+            (0, Element::LeftSplitter) => down(j.checked_sub(1)),
+            (0, Element::RightSplitter) => down(Some(j + 1)),
+            (0, Element::Splitter) => add(down(j.checked_sub(1)), down(Some(j + 1))),
+            (-1, Element::Empty) => walk(tachyon, i, j.checked_sub(1), -1),
+            (1, Element::Empty) => walk(tachyon, i, Some(j + 1), 1),
+            (-1, Element::MirrorLeft) | (1, Element::MirrorRight) => {
+                walk(tachyon, i + 1, Some(j), 0)
+            }
+            _ => (0, 1),
+        }
+    }
+
+    #[test]
+    fn seventh_test_elements() {
+        let data = "\
+            .S...S.\n\
+            .......\n\
+            ./...\\\\\n\
+            .....#.\n\
+            \\.<...<\n\
+            .......\n";
+        let stats = Tachyon::new().source_stats(data);
+        assert_eq!(stats.len(), 2);
+        // first beam turns left, leaves at the left edge
+        assert_eq!((stats[0].row, stats[0].col, stats[0].splits), (0, 1, 0));
+        assert_eq!(stats[0].timelines.left, BigUint::from(1u32));
+        assert_eq!(stats[0].timelines.total(), BigUint::from(1u32));
+        // second beam turns right, is turned down at column 6 and splits only left
+        assert_eq!(stats[1].splits, 1);
+        assert_eq!(stats[1].timelines.columns[5], BigUint::from(1u32));
+        assert_eq!(stats[1].timelines.total(), BigUint::from(1u32));
+        assert_eq!(stats[1].absorbed, BigUint::ZERO);
+
+        // beam moving left is turned down by `/` and absorbed by `#`
+        let data = "...S\n/../\n#...\n";
+        let stats = Tachyon::new().source_stats(data);
+        assert_eq!(stats[0].timelines.total(), BigUint::ZERO);
+        assert_eq!(stats[0].absorbed, BigUint::from(1u32));
+    }
+
+    #[test]
+    fn seventh_test_elements_random() {
+        let mut rng = Rng(0x5eed_0007);
+        let chars = b"......^<>/\\#";
+        for _ in 0..500 {
+            let width = 1 + rng.next(8) as usize;
+            let height = 1 + rng.next(8) as usize;
+            let col = rng.next(width as u64) as usize;
+            let mut data = format!("{}S{}\n", ".".repeat(col), ".".repeat(width - col - 1));
+            for _ in 0..height {
+                let row: Vec<u8> = (0..width)
+                    .map(|_| chars[rng.next(chars.len() as u64) as usize])
+                    .collect();
+                data.push_str(std::str::from_utf8(&row).unwrap());
+                data.push('\n');
+            }
+
+            let mut tachyon = Tachyon::new();
+            let stats = tachyon.source_stats(&data);
+            let (exited, absorbed) = walk(&tachyon, 1, Some(col), 0);
+            assert_eq!(stats[0].timelines.total(), BigUint::from(exited), "{data}");
+            assert_eq!(stats[0].absorbed, BigUint::from(absorbed), "{data}");
+            assert_eq!(tachyon.part2(&data), exited.to_string());
+            assert_eq!(tachyon.part1(&data), stats[0].splits.to_string());
+        }
+    }
+
+    #[test]
+    fn seventh_test_cell_below_source() {
+        // the cell directly below a source is the first one a beam enters
+        let data = "..S..\n..^..\n.....\n";
+        let mut tachyon = Tachyon::new();
+        assert_eq!(tachyon.part1(data), "1");
+        assert_eq!(tachyon.part2(data), "2");
+        let stats = tachyon.source_stats(data);
+        assert_eq!(tachyon.part1(data), stats[0].splits.to_string());
+    }
 }
@@ -1,8 +1,181 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, anyhow};
+use num_bigint::BigUint;
+
 use crate::utils::Exercise;
 
+/// Worksheet operator, folds operands of a problem from left to right.
+/// Both folds return `None` when the result does not fit or is undefined.
+#[derive(Clone, Copy)]
+pub struct Operator {
+    pub symbol: char,
+    pub name: &'static str,
+    pub apply: fn(u64, u64) -> Option<u64>,
+    pub apply_big: fn(&BigUint, &BigUint) -> Option<BigUint>,
+}
+
+/// Longest power computed in arbitrary precision, in bits.
+const MAX_POW_BITS: u64 = 1 << 24;
+
+/// Built-in operators.
+pub const OPERATORS: [Operator; 7] = [
+    Operator {
+        symbol: '+',
+        name: "add",
+        apply: u64::checked_add,
+        apply_big: |a, b| Some(a + b),
+    },
+    Operator {
+        symbol: '*',
+        name: "mul",
+        apply: u64::checked_mul,
+        apply_big: |a, b| Some(a * b),
+    },
+    Operator {
+        symbol: '-',
+        name: "sub",
+        apply: u64::checked_sub,
+        apply_big: |a, b| (a >= b).then(|| a - b),
+    },
+    Operator {
+        symbol: '/',
+        name: "div",
+        apply: u64::checked_div,
+        apply_big: |a, b| (*b != BigUint::ZERO).then(|| a / b),
+    },
+    Operator {
+        symbol: '<',
+        name: "min",
+        apply: |a, b| Some(a.min(b)),
+        apply_big: |a, b| Some(a.min(b).clone()),
+    },
+    Operator {
+        symbol: '>',
+        name: "max",
+        apply: |a, b| Some(a.max(b)),
+        apply_big: |a, b| Some(a.max(b).clone()),
+    },
+    Operator {
+        symbol: '^',
+        name: "pow",
+        apply: |a, b| a.checked_pow(b.try_into().ok()?),
+        apply_big: |a, b| {
+            let exp = u32::try_from(b).ok()?;
+            (a.bits() * exp as u64 <= MAX_POW_BITS).then(|| a.pow(exp))
+        },
+    },
+];
+
+/// Worksheet layouts of day 6.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Every problem is a column of numbers written left to right.
+    Human,
+    /// Every number is a column of digits written top to bottom.
+    Cephalopod,
+}
+
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "human" => Ok(Layout::Human),
+            "cephalopod" => Ok(Layout::Cephalopod),
+            _ => Err(anyhow!("Unknown layout {s}")),
+        }
+    }
+}
+
+/// One reading of a worksheet problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reading {
+    pub operands: Vec<u64>,
+    pub op: char,
+    /// `None` when the result overflows or is undefined.
+    pub result: Option<String>,
+}
+
+impl Reading {
+    fn json(&self) -> String {
+        let operands: Vec<String> = self.operands.iter().map(|n| n.to_string()).collect();
+        let op = match self.op {
+            '"' | '\\' => format!("\\{}", self.op),
+            op => op.to_string(),
+        };
+        format!(
+            "{{\"op\": \"{op}\", \"operands\": [{}], \"result\": {}}}",
+            operands.join(", "),
+            self.result.as_deref().unwrap_or("null")
+        )
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operands: Vec<String> = self.operands.iter().map(|n| n.to_string()).collect();
+        write!(
+            f,
+            "{} = {}",
+            operands.join(&format!(" {} ", self.op)),
+            self.result.as_deref().unwrap_or("error")
+        )
+    }
+}
+
+/// Every problem of a worksheet in the human and cephalopod readings.
+pub struct Breakdown {
+    pub problems: Vec<(Reading, Reading)>,
+}
+
+impl Breakdown {
+    /// Whether the readings of problem `i` give different results.
+    pub fn differs(&self, i: usize) -> bool {
+        self.problems[i].0.result != self.problems[i].1.result
+    }
+
+    /// Hand formatted JSON with a problem per line.
+    pub fn json(&self) -> String {
+        let rows: Vec<String> = self
+            .problems
+            .iter()
+            .enumerate()
+            .map(|(i, (human, cephalopod))| {
+                format!(
+                    "  {{\"problem\": {}, \"differs\": {}, \"human\": {}, \"cephalopod\": {}}}",
+                    i + 1,
+                    self.differs(i),
+                    human.json(),
+                    cephalopod.json()
+                )
+            })
+            .collect();
+        format!("{{\"problems\": [\n{}\n]}}", rows.join(",\n"))
+    }
+}
+
+impl fmt::Display for Breakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "problem\thuman\tcephalopod\tdiffers")?;
+        for (i, (human, cephalopod)) in self.problems.iter().enumerate() {
+            let differs = if self.differs(i) { "yes" } else { "no" };
+            writeln!(f, "{}\t{human}\t{cephalopod}\t{differs}", i + 1)?;
+        }
+        let differ = (0..self.problems.len())
+            .filter(|&i| self.differs(i))
+            .count();
+        write!(f, "differ {differ} of {}", self.problems.len())
+    }
+}
+
 pub struct MathProblem {
     data: Vec<Vec<u64>>,
     ops: Vec<char>,
+    /// Known operators, `OPERATORS` by default.
+    operators: Vec<Operator>,
+    /// Computes in arbitrary precision instead of checked `u64`.
+    exact: bool,
 }
 
 impl MathProblem {
@@ -10,7 +183,41 @@ impl MathProblem {
         Self {
             data: Vec::new(),
             ops: Vec::new(),
+            operators: OPERATORS.to_vec(),
+            exact: false,
+        }
+    }
+
+    pub fn with_exact(mut self, exact: bool) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    /// Adds operator or replaces the one with the same symbol.
+    pub fn with_operator(mut self, op: Operator) -> Self {
+        self.operators.retain(|o| o.symbol != op.symbol);
+        self.operators.push(op);
+        self
+    }
+
+    fn operator(&self, symbol: char) -> Option<&Operator> {
+        self.operators.iter().find(|o| o.symbol == symbol)
+    }
+
+    fn push_op(&mut self, symbol: char, col: usize) -> Result<()> {
+        if self.operator(symbol).is_none() {
+            return Err(anyhow!("unknown operator '{symbol}' in column {col}"));
+        }
+        self.ops.push(symbol);
+        Ok(())
+    }
+
+    /// Reads operator row, every operator is the first char of its token.
+    fn parse_ops(&mut self, line: &str) -> Result<()> {
+        for (col, token) in line.split_whitespace().enumerate() {
+            self.push_op(token.chars().next().unwrap(), col + 1)?;
         }
+        Ok(())
     }
 
     pub fn clear(&mut self) {
@@ -18,88 +225,306 @@ impl MathProblem {
         self.ops.clear();
     }
 
-    fn fill(&mut self, data: &str) {
-        let first_line = data.lines().next().unwrap();
-        for num in first_line.split_whitespace() {
-            let num: u64 = num.parse().unwrap();
-            self.data.push(vec![num]);
-        }
-
-        for line in data.lines().skip(1) {
-            if line.starts_with(&['+', '*']) {
-                self.ops.extend(
-                    line.trim()
-                        .split_whitespace()
-                        .map(|s| s.chars().nth(0).unwrap()),
-                );
-            } else {
-                for (i, num) in line
-                    .split_whitespace()
-                    .map(|s| s.parse().unwrap())
-                    .enumerate()
-                {
-                    self.data[i].push(num);
+    /// Reads worksheet with a problem in every column and operators in the last row.
+    fn fill(&mut self, data: &str) -> Result<()> {
+        let lines: Vec<&str> = data.lines().filter(|l| !l.trim().is_empty()).collect();
+        let (op_line, num_lines) = lines.split_last().ok_or(anyhow!("empty worksheet"))?;
+
+        for (row, line) in num_lines.iter().enumerate() {
+            for (i, num) in line.split_whitespace().enumerate() {
+                let num: u64 = num
+                    .parse()
+                    .map_err(|e| anyhow!("row {}, column {}: {e}", row + 1, i + 1))?;
+                if row == 0 {
+                    self.data.push(Vec::new());
                 }
+                self.data
+                    .get_mut(i)
+                    .ok_or(anyhow!("row {} has extra column {}", row + 1, i + 1))?
+                    .push(num);
             }
         }
+
+        self.parse_ops(op_line)?;
+        if self.ops.len() != self.data.len() {
+            return Err(anyhow!(
+                "{} operators for {} problems",
+                self.ops.len(),
+                self.data.len()
+            ));
+        }
+        Ok(())
     }
 
-    fn cephalopod_fill(&mut self, data: &str) {
-        let width = data.lines().next().unwrap().len() + 1; // +'\n'
-        let idx = |i: usize, j: usize| -> usize { i * width + j };
+    /// Reads worksheet with numbers written top to bottom in columns. Lines are
+    /// padded with spaces to the same width, problems are separated by columns
+    /// which are blank in every row and the operator may be anywhere under its
+    /// problem.
+    fn cephalopod_fill(&mut self, data: &str) -> Result<()> {
+        let mut lines: Vec<&[u8]> = data.lines().map(str::as_bytes).collect();
+        while lines.last().is_some_and(|l| l.trim_ascii().is_empty()) {
+            lines.pop();
+        }
+        let (op_line, num_lines) = lines.split_last().ok_or(anyhow!("empty worksheet"))?;
+        let width = lines.iter().map(|l| l.len()).max().unwrap_or_default();
+        let at = |line: &[u8], col: usize| line.get(col).copied().unwrap_or(b' ');
+        let is_blank = |col: usize| lines.iter().all(|l| at(l, col).is_ascii_whitespace());
 
-        // get all areas with numbers
-        let bytes = data.as_bytes();
-        let row = data.trim().lines().count();
         let mut col = 0;
         while col < width {
-            let beg_row = 0;
-            let beg_col = col;
-
-            // end of the area when bytes in all rows are spaces or '\n'
-            let mut has_digit = true;
-            while has_digit {
+            if is_blank(col) {
                 col += 1;
-                has_digit = false;
-                for i in 0..row {
-                    if bytes[idx(i, col)].is_ascii_digit() {
-                        has_digit = true;
+                continue;
+            }
+            let problem = self.data.len() + 1;
+            let mut nums = Vec::new();
+            let mut op = None;
+            while col < width && !is_blank(col) {
+                let mut num = None;
+                for line in num_lines {
+                    match at(line, col) {
+                        b if b.is_ascii_digit() => {
+                            let value = num
+                                .unwrap_or(0u64)
+                                .checked_mul(10)
+                                .and_then(|n| n.checked_add((b - b'0') as u64))
+                                .ok_or(anyhow!("number too large in column {problem}"))?;
+                            num = Some(value);
+                        }
+                        b if b.is_ascii_whitespace() => (),
+                        b => {
+                            return Err(anyhow!(
+                                "invalid character '{}' in column {problem}",
+                                b as char
+                            ));
+                        }
                     }
                 }
-            }
-
-            // read numbers row by row
-            self.data.push(Vec::new());
-            for j in beg_col..col {
-                let mut num = 0;
-                for i in beg_row..row {
-                    if bytes[idx(i, j)].is_ascii_digit() {
-                        num = num * 10 + (bytes[idx(i, j)] - b'0') as u64;
+                nums.extend(num);
+                let symbol = at(op_line, col);
+                if !symbol.is_ascii_whitespace() {
+                    if op.is_some() {
+                        return Err(anyhow!("several operators in column {problem}"));
                     }
+                    op = Some(symbol as char);
                 }
-                self.data.last_mut().unwrap().push(num);
+                col += 1;
             }
+            let op = op.ok_or(anyhow!("missing operator in column {problem}"))?;
+            if nums.is_empty() {
+                return Err(anyhow!("missing numbers in column {problem}"));
+            }
+            self.push_op(op, problem)?;
+            self.data.push(nums);
+        }
+        Ok(())
+    }
+
+    /// Result of problem `i` in checked `u64`.
+    fn solve(&self, i: usize) -> Result<u64> {
+        // parsed operators are always known
+        let op = self.operator(self.ops[i]).unwrap();
+        let mut nums = self.data[i].iter().copied();
+        let first = nums.next().unwrap_or_default();
+        match nums.try_fold(first, op.apply) {
+            Some(value) => Ok(value),
+            // undefined results are errors in both modes, the rest overflowed
+            None => Err(match self.solve_big(i) {
+                Ok(_) => anyhow!("problem {} ({}) overflowed", i + 1, op.name),
+                Err(e) => e,
+            }),
+        }
+    }
 
-            col += 1;
+    /// Result of problem `i` in arbitrary precision.
+    fn solve_big(&self, i: usize) -> Result<BigUint> {
+        let op = self.operator(self.ops[i]).unwrap();
+        let mut nums = self.data[i].iter().map(|&n| BigUint::from(n));
+        let first = nums.next().unwrap_or_default();
+        nums.try_fold(first, |acc, n| (op.apply_big)(&acc, &n))
+            .ok_or(anyhow!("problem {} ({}) is out of range", i + 1, op.name))
+    }
+
+    /// Grand total in checked `u64`, errors name the first problem which overflowed.
+    fn do_math(&self) -> Result<u64> {
+        let mut total: u64 = 0;
+        for i in 0..self.ops.len() {
+            total = total
+                .checked_add(self.solve(i)?)
+                .ok_or(anyhow!("grand total overflowed at problem {}", i + 1))?;
         }
+        Ok(total)
+    }
 
-        // get operators
-        for op in data.trim().lines().last().unwrap().split_whitespace() {
-            self.ops.push(op.chars().next().unwrap());
+    /// Grand total in arbitrary precision, only undefined results are errors.
+    fn do_math_big(&self) -> Result<BigUint> {
+        let mut total = BigUint::ZERO;
+        for i in 0..self.ops.len() {
+            total += self.solve_big(i)?;
         }
+        Ok(total)
     }
 
-    fn do_math(&mut self) -> u64 {
-        let mut total = 0;
-        for (i, op) in self.ops.iter().enumerate() {
-            let value: u64 = match op {
-                '+' => self.data[i].iter().sum(),
-                '*' => self.data[i].iter().product(),
-                _ => panic!("invalid op"),
-            };
-            total += value;
+    fn total(&self) -> Result<String> {
+        match self.exact {
+            true => Ok(self.do_math_big()?.to_string()),
+            false => Ok(self.do_math()?.to_string()),
         }
-        total
+    }
+
+    /// Result of every problem in the current precision mode.
+    fn results(&self) -> Result<Vec<String>> {
+        (0..self.ops.len())
+            .map(|i| match self.exact {
+                true => Ok(self.solve_big(i)?.to_string()),
+                false => Ok(self.solve(i)?.to_string()),
+            })
+            .collect()
+    }
+
+    /// Every problem with operands and result, overflow is kept as `None`.
+    fn readings(&self) -> Vec<Reading> {
+        (0..self.ops.len())
+            .map(|i| Reading {
+                operands: self.data[i].clone(),
+                op: self.ops[i],
+                result: match self.exact {
+                    true => self.solve_big(i).ok().map(|r| r.to_string()),
+                    false => self.solve(i).ok().map(|r| r.to_string()),
+                },
+            })
+            .collect()
+    }
+
+    /// Reads `data` in both layouts and pairs up their problems.
+    pub fn breakdown(&mut self, data: &str) -> Result<Breakdown> {
+        self.read(data, Layout::Human)?;
+        let human = self.readings();
+        self.read(data, Layout::Cephalopod)?;
+        let cephalopod = self.readings();
+        if human.len() != cephalopod.len() {
+            return Err(anyhow!(
+                "{} problems in human reading, {} in cephalopod one",
+                human.len(),
+                cephalopod.len()
+            ));
+        }
+        Ok(Breakdown {
+            problems: human.into_iter().zip(cephalopod).collect(),
+        })
+    }
+
+    /// Clears the worksheet and reads `data` in the given layout.
+    pub fn read(&mut self, data: &str, layout: Layout) -> Result<()> {
+        self.clear();
+        match layout {
+            Layout::Human => self.fill(data),
+            Layout::Cephalopod => self.cephalopod_fill(data),
+        }
+    }
+
+    /// Writes the worksheet in the given layout. Reading the output back gives
+    /// the same problems unless `results` appends a row with every problem's
+    /// result under the operators.
+    pub fn write(&self, layout: Layout, results: bool) -> Result<String> {
+        let results = match results {
+            true => Some(self.results()?),
+            false => None,
+        };
+        match layout {
+            Layout::Human => self.write_human(results),
+            Layout::Cephalopod => Ok(self.write_cephalopod(results)),
+        }
+    }
+
+    /// Every problem is a right aligned column, all of them need the same
+    /// number of operands.
+    fn write_human(&self, results: Option<Vec<String>>) -> Result<String> {
+        let rows = self.data.first().map_or(0, |d| d.len());
+        if let Some(i) = self.data.iter().position(|d| d.len() != rows) {
+            return Err(anyhow!(
+                "problem {} has {} operands, human layout needs {rows}",
+                i + 1,
+                self.data[i].len()
+            ));
+        }
+
+        let mut cells: Vec<Vec<String>> = (0..rows)
+            .map(|row| self.data.iter().map(|d| d[row].to_string()).collect())
+            .collect();
+        cells.push(self.ops.iter().map(|op| op.to_string()).collect());
+        cells.extend(results);
+        let widths: Vec<usize> = (0..self.ops.len())
+            .map(|i| cells.iter().map(|row| row[i].len()).max().unwrap())
+            .collect();
+
+        let mut out = String::new();
+        for (row, line) in cells.iter().enumerate() {
+            let cols: Vec<String> = line
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &w)| match row == rows {
+                    true => format!("{cell:<w$}"),
+                    false => format!("{cell:>w$}"),
+                })
+                .collect();
+            out.push_str(cols.join(" ").trim_end());
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Every operand is a column with digits ending on the last number row,
+    /// the operator is under the left edge of its problem.
+    fn write_cephalopod(&self, results: Option<Vec<String>>) -> String {
+        let nums: Vec<Vec<String>> = self
+            .data
+            .iter()
+            .map(|d| d.iter().map(|n| n.to_string()).collect())
+            .collect();
+        let rows = nums.iter().flatten().map(|n| n.len()).max().unwrap_or(0);
+        let widths: Vec<usize> = nums
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let result = results.as_ref().map_or(0, |r| r[i].len());
+                d.len().max(result)
+            })
+            .collect();
+
+        let mut lines = vec![String::new(); rows + 1];
+        for (i, problem) in nums.iter().enumerate() {
+            if i > 0 {
+                lines.iter_mut().for_each(|l| l.push(' '));
+            }
+            for (row, line) in lines[..rows].iter_mut().enumerate() {
+                for j in 0..widths[i] {
+                    let digit = problem.get(j).and_then(|n| {
+                        // pad with spaces on top
+                        let skip = rows - n.len();
+                        (row >= skip).then(|| n.as_bytes()[row - skip] as char)
+                    });
+                    line.push(digit.unwrap_or(' '));
+                }
+            }
+            let w = widths[i];
+            lines[rows].push_str(&format!("{:<w$}", self.ops[i]));
+        }
+        if let Some(results) = results {
+            let cols: Vec<String> = results
+                .iter()
+                .zip(&widths)
+                .map(|(r, &w)| format!("{r:<w$}"))
+                .collect();
+            lines.push(cols.join(" "));
+        }
+
+        let mut out = String::new();
+        for line in lines {
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
     }
 }
 
@@ -110,20 +535,20 @@ impl Exercise for MathProblem {
 
     fn part1(&mut self, data: &str) -> String {
         self.clear();
-        self.fill(data);
-        self.do_math().to_string()
+        self.fill(data).unwrap();
+        self.total().unwrap()
     }
 
     fn part2(&mut self, data: &str) -> String {
         self.clear();
-        self.cephalopod_fill(data);
-        self.do_math().to_string()
+        self.cephalopod_fill(data).unwrap();
+        self.total().unwrap()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils;
+    use crate::utils::{self, Rng};
 
     use super::*;
 
@@ -142,4 +567,229 @@ mod tests {
         let res = problem.part2(&data);
         assert_eq!(res, "3263827");
     }
+
+    #[test]
+    fn sixth_test_operators() {
+        let data = "20 7 9 2 100 3\n 5 2 3 9 10  4\n 2 1 1 4 20  2\n-  / < > -   ^\n";
+        let mut problem = MathProblem::new();
+        problem.fill(data).unwrap();
+        // 13 + 3 + 1 + 9 + 70 + 3^4^2
+        assert_eq!(problem.do_math().unwrap(), 13 + 3 + 1 + 9 + 70 + 6561);
+
+        let data = "26 4\n15 6\n-  ^\n";
+        let mut problem = MathProblem::new();
+        problem.cephalopod_fill(data).unwrap();
+        // columns read top to bottom: 21 - 65, 46
+        assert_eq!(problem.data, vec![vec![21, 65], vec![46]]);
+
+        let mut problem = MathProblem::new();
+        let err = problem.fill("1 2 3\n4 5 6\n+ % *").unwrap_err();
+        assert_eq!(err.to_string(), "unknown operator '%' in column 2");
+
+        let mut problem = MathProblem::new().with_operator(Operator {
+            symbol: '%',
+            name: "rem",
+            apply: u64::checked_rem,
+            apply_big: |a, b| (*b != BigUint::ZERO).then(|| a % b),
+        });
+        problem.fill("7 2 3\n4 5 6\n% + *").unwrap();
+        assert_eq!(problem.do_math().unwrap(), 3 + 7 + 18);
+    }
+
+    #[test]
+    fn sixth_test_overflow() {
+        let data = "5 4294967296 2\n7 4294967296 3\n* * -\n";
+        let mut problem = MathProblem::new();
+        problem.fill(data).unwrap();
+        let err = problem.do_math().unwrap_err();
+        assert_eq!(err.to_string(), "problem 2 (mul) overflowed");
+        let err = problem.do_math_big().unwrap_err();
+        assert_eq!(err.to_string(), "problem 3 (sub) is out of range");
+
+        let mut problem = MathProblem::new().with_exact(true);
+        let res = problem.part1("5 4294967296 2\n7 4294967296 3\n* * +\n");
+        assert_eq!(res, ((BigUint::from(1u64) << 64u32) + 40u32).to_string());
+
+        let data = "18446744073709551615 1\n+ +\n";
+        let mut problem = MathProblem::new();
+        problem.fill(data).unwrap();
+        let err = problem.do_math().unwrap_err();
+        assert_eq!(err.to_string(), "grand total overflowed at problem 2");
+        assert_eq!(problem.do_math_big().unwrap(), BigUint::from(1u64) << 64u32);
+
+        // exact mode agrees with checked one when nothing overflows
+        let data = utils::read_data(6, "test1").unwrap();
+        let mut exact = MathProblem::new().with_exact(true);
+        assert_eq!(exact.part1(&data), MathProblem::new().part1(&data));
+        assert_eq!(exact.part2(&data), MathProblem::new().part2(&data));
+    }
+
+    #[test]
+    fn sixth_test_ragged() {
+        let expected = vec![vec![1, 24, 356], vec![369, 248, 8], vec![32, 581, 175]];
+
+        // trailing spaces trimmed, operator row shorter than the others
+        let data = "123 328  51\n 45 64  387\n  6 98  215\n*   +   +\n";
+        let mut problem = MathProblem::new();
+        problem.cephalopod_fill(data).unwrap();
+        assert_eq!(problem.data, expected);
+        assert_eq!(problem.ops, vec!['*', '+', '+']);
+
+        // trailing whitespace and blank lines after operators
+        let data = "123 328  51   \n 45 64  387   \n  6 98  215   \n*   +   +     \n  \n\n";
+        let mut problem = MathProblem::new();
+        problem.cephalopod_fill(data).unwrap();
+        assert_eq!(problem.data, expected);
+
+        // operators aligned to the right or middle of their problems
+        let data = "123 328  51\n 45 64  387\n  6 98  215\n  *  +    +\n";
+        let mut problem = MathProblem::new();
+        problem.cephalopod_fill(data).unwrap();
+        assert_eq!(problem.data, expected);
+        assert_eq!(problem.ops, vec!['*', '+', '+']);
+
+        let mut problem = MathProblem::new();
+        let err = problem.cephalopod_fill("12 34\n56 78\n+\n").unwrap_err();
+        assert_eq!(err.to_string(), "missing operator in column 2");
+        let mut problem = MathProblem::new();
+        let err = problem.cephalopod_fill("12 34\n56 78\n+  ?\n").unwrap_err();
+        assert_eq!(err.to_string(), "unknown operator '?' in column 2");
+        let mut problem = MathProblem::new();
+        let err = problem.cephalopod_fill("12 34\n56 78\n++ *\n").unwrap_err();
+        assert_eq!(err.to_string(), "several operators in column 1");
+
+        // a column up to u64::MAX fits, a longer one is an error
+        let column = |digits: &str| {
+            let mut data: String = digits.chars().map(|d| format!("0 {d}\n")).collect();
+            data.push_str("+ +\n");
+            data
+        };
+        let mut problem = MathProblem::new().with_exact(true);
+        let res = problem.part2(&column(&u64::MAX.to_string()));
+        assert_eq!(res, u64::MAX.to_string());
+        let mut problem = MathProblem::new().with_exact(true);
+        let err = problem
+            .cephalopod_fill(&column(&"9".repeat(21)))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "number too large in column 2");
+    }
+
+    #[test]
+    fn sixth_test_convert() {
+        let data = utils::read_data(6, "test1").unwrap();
+        for layout in [Layout::Human, Layout::Cephalopod] {
+            let other = match layout {
+                Layout::Human => Layout::Cephalopod,
+                Layout::Cephalopod => Layout::Human,
+            };
+            let mut problem = MathProblem::new();
+            problem.read(&data, layout).unwrap();
+            let converted = problem.write(other, false).unwrap();
+            let mut back = MathProblem::new();
+            back.read(&converted, other).unwrap();
+            assert_eq!(back.data, problem.data);
+            assert_eq!(back.ops, problem.ops);
+            assert_eq!(back.write(other, false).unwrap(), converted);
+        }
+
+        let mut problem = MathProblem::new();
+        problem.read(&data, Layout::Human).unwrap();
+        assert_eq!(
+            problem.write(Layout::Human, true).unwrap(),
+            "  123 328      51  64\n   45  64     387  23\n    6  98     215 314\n\
+             *     +   *       +\n33210 490 4243455 401\n"
+        );
+        assert_eq!(
+            problem.write(Layout::Cephalopod, true).unwrap(),
+            "1     3    32       3\n24    269 581     621\n356   848 175     434\n\
+             *     +   *       +\n33210 490 4243455 401\n"
+        );
+    }
+
+    #[test]
+    fn sixth_test_convert_random() {
+        let mut rng = Rng(0x5eed_0006);
+        for _ in 0..200 {
+            let problems = 1 + rng.next(6) as usize;
+            let rows = 1 + rng.next(4) as usize;
+            let mut problem = MathProblem::new();
+            for _ in 0..problems {
+                let nums = (0..rows).map(|_| {
+                    let digits = rng.next(7) as u32;
+                    rng.next(10u64.pow(digits) + 1)
+                });
+                problem.data.push(nums.collect());
+                problem.ops.push(['+', '*', '<', '>'][rng.next(4) as usize]);
+            }
+
+            let human = problem.write(Layout::Human, false).unwrap();
+            let cephalopod = problem.write(Layout::Cephalopod, false).unwrap();
+            let mut back = MathProblem::new();
+            back.read(&cephalopod, Layout::Cephalopod).unwrap();
+            assert_eq!(back.data, problem.data);
+            assert_eq!(back.ops, problem.ops);
+            assert_eq!(back.write(Layout::Human, false).unwrap(), human);
+            back.read(&human, Layout::Human).unwrap();
+            assert_eq!(back.data, problem.data);
+            assert_eq!(back.write(Layout::Cephalopod, false).unwrap(), cephalopod);
+        }
+    }
+
+    #[test]
+    fn sixth_test_breakdown() {
+        let data = utils::read_data(6, "test1").unwrap();
+        let breakdown = MathProblem::new().breakdown(&data).unwrap();
+        assert_eq!(
+            breakdown.to_string(),
+            "problem\thuman\tcephalopod\tdiffers\n\
+             1\t123 * 45 * 6 = 33210\t1 * 24 * 356 = 8544\tyes\n\
+             2\t328 + 64 + 98 = 490\t369 + 248 + 8 = 625\tyes\n\
+             3\t51 * 387 * 215 = 4243455\t32 * 581 * 175 = 3253600\tyes\n\
+             4\t64 + 23 + 314 = 401\t623 + 431 + 4 = 1058\tyes\n\
+             differ 4 of 4"
+        );
+        assert!(breakdown.json().starts_with(
+            "{\"problems\": [\n  {\"problem\": 1, \"differs\": true, \
+             \"human\": {\"op\": \"*\", \"operands\": [123, 45, 6], \"result\": 33210}, \
+             \"cephalopod\": {\"op\": \"*\", \"operands\": [1, 24, 356], \"result\": 8544}},\n"
+        ));
+
+        // single digit rows read the same way, overflow is kept per problem
+        let breakdown = MathProblem::new().breakdown("7 12\n+ *\n").unwrap();
+        assert!(!breakdown.differs(0));
+        assert!(breakdown.differs(1));
+        let data = "4294967296 5\n4294967296 6\n*          *\n";
+        let breakdown = MathProblem::new().breakdown(data).unwrap();
+        assert_eq!(breakdown.problems[0].0.result, None);
+        assert_eq!(breakdown.problems[1].0.result, Some("30".to_string()));
+        assert!(breakdown.to_string().contains("= error"));
+        assert!(breakdown.json().contains("\"result\": null}"));
+    }
+
+    #[test]
+    fn sixth_test_undefined_operators() {
+        // smaller minuend, zero divisor and large exponents never panic
+        let data = "3 8 2  2          7\n5 0 100 4294967296 10\n- / ^  ^          /\n";
+        for exact in [false, true] {
+            let mut problem = MathProblem::new().with_exact(exact);
+            problem.fill(data).unwrap();
+            let err = |i| match exact {
+                false => problem.solve(i).unwrap_err().to_string(),
+                true => problem.solve_big(i).unwrap_err().to_string(),
+            };
+            assert_eq!(err(0), "problem 1 (sub) is out of range");
+            assert_eq!(err(1), "problem 2 (div) is out of range");
+            assert_eq!(err(3), "problem 4 (pow) is out of range");
+            assert_eq!(problem.results().unwrap_err().to_string(), err(0));
+        }
+
+        let mut problem = MathProblem::new();
+        problem.fill(data).unwrap();
+        assert_eq!(
+            problem.solve(2).unwrap_err().to_string(),
+            "problem 3 (pow) overflowed"
+        );
+        assert_eq!(problem.solve(4).unwrap(), 0);
+        assert_eq!(problem.solve_big(2).unwrap(), BigUint::from(1u32) << 100u32);
+    }
 }
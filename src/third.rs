@@ -1,9 +1,86 @@
+use std::{collections::BTreeSet, fmt};
+
 use crate::utils::Exercise;
+use anyhow::{Result, anyhow};
 use itertools::Itertools;
+use num_bigint::BigUint;
+
+/// Extra rules for choosing batteries in a bank.
+#[derive(Default, Clone, Debug)]
+pub struct Constraints {
+    /// Positions which must be kept.
+    pub include: Vec<usize>,
+    /// Positions which must not be kept.
+    pub exclude: Vec<usize>,
+    /// Most batteries skipped between two kept ones.
+    pub max_gap: Option<usize>,
+}
+
+impl Constraints {
+    /// `(excluded, next_forced)` where `next_forced[i]` is the first forced
+    /// position at or after `i` (or `n` if there is none).
+    fn masks(&self, n: usize) -> Result<(Vec<bool>, Vec<usize>)> {
+        let mut excluded = vec![false; n];
+        for &i in self.exclude.iter().filter(|&&i| i < n) {
+            excluded[i] = true;
+        }
+
+        let mut forced = vec![false; n];
+        for &i in self.include.iter() {
+            if i >= n || excluded[i] {
+                return Err(anyhow!("position {i} can not be kept"));
+            }
+            forced[i] = true;
+        }
+
+        let mut next_forced = vec![n; n + 1];
+        for i in (0..n).rev() {
+            next_forced[i] = if forced[i] { i } else { next_forced[i + 1] };
+        }
+
+        Ok((excluded, next_forced))
+    }
+
+    /// Checks positions chosen by brute force.
+    fn allows(&self, selected: &[usize]) -> bool {
+        self.include.iter().all(|i| selected.contains(i))
+            && !self.exclude.iter().any(|i| selected.contains(i))
+            && self
+                .max_gap
+                .is_none_or(|gap| selected.windows(2).all(|w| w[1] - w[0] - 1 <= gap))
+    }
+}
+
+/// Digits given to every bank under a shared battery budget.
+#[derive(Debug)]
+pub struct Allocation {
+    /// Amount of kept batteries per bank.
+    pub digits: Vec<usize>,
+    /// Maximum joltage per bank for its amount of digits.
+    pub values: Vec<BigUint>,
+    pub total: BigUint,
+}
+
+impl fmt::Display for Allocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (k, value)) in self.digits.iter().zip(&self.values).enumerate() {
+            writeln!(f, "bank {}\t{k}\t{value}", i + 1)?;
+        }
+        write!(
+            f,
+            "total\t{}\t{}",
+            self.digits.iter().sum::<usize>(),
+            self.total
+        )
+    }
+}
 
 pub struct JoltageBank;
 
 impl JoltageBank {
+    /// Any 19-digit number fits into `u64`.
+    const U64_DIGITS: u64 = 19;
+
     pub fn new() -> Self {
         Self {}
     }
@@ -24,15 +101,287 @@ impl JoltageBank {
     ///
     /// Walks through data and removes smaller elements
     /// to get maximum possible joltage.
-    pub fn max_joltage_fast(digs: &[u8], max_digits: u64) -> u64 {
+    ///
+    /// Fails for banks shorter than `max_digits` and for values
+    /// which do not fit into `u64` (more than 19 digits).
+    pub fn max_joltage_fast(digs: &[u8], max_digits: u64) -> Result<u64> {
+        Ok(JoltageBank::select_fast(digs, max_digits)?.1)
+    }
+
+    /// O(digs.len())
+    ///
+    /// Same as `max_joltage_fast`, but also returns ascending positions
+    /// of batteries kept in the bank.
+    pub fn select_fast(digs: &[u8], max_digits: u64) -> Result<(Vec<usize>, u64)> {
+        if max_digits > JoltageBank::U64_DIGITS {
+            return Err(anyhow!(
+                "{max_digits} digits do not fit into u64, use max_joltage_big"
+            ));
+        }
+
+        let selected = JoltageBank::select_positions(digs, max_digits as usize)?;
+        let value = selected.iter().fold(0, |acc, &i| 10 * acc + digs[i] as u64);
+        Ok((selected, value))
+    }
+
+    /// O(digs.len())
+    ///
+    /// Ascending positions of `k` batteries giving maximum joltage.
+    fn select_positions(digs: &[u8], k: usize) -> Result<Vec<usize>> {
         let n = digs.len();
-        let k = max_digits as usize;
+        if n < k {
+            return Err(anyhow!("bank of {n} batteries is shorter than {k} digits"));
+        }
 
-        let mut stack = Vec::with_capacity(k);
+        let mut stack: Vec<usize> = Vec::with_capacity(k);
+        let mut to_remove = n - k;
+
+        for (i, &d) in digs.iter().enumerate() {
+            while to_remove > 0 && !stack.is_empty() && digs[*stack.last().unwrap()] < d {
+                stack.pop();
+                to_remove -= 1;
+            }
+            stack.push(i);
+        }
+
+        stack.truncate(k);
+        Ok(stack)
+    }
+
+    /// O(digs.len())
+    ///
+    /// Maximum joltage for any `max_digits` as decimal digits.
+    pub fn max_joltage_digits(digs: &[u8], max_digits: usize) -> Result<String> {
+        Ok(JoltageBank::select_positions(digs, max_digits)?
+            .into_iter()
+            .map(|i| (b'0' + digs[i]) as char)
+            .collect())
+    }
+
+    /// O(digs.len())
+    ///
+    /// Maximum joltage for any `max_digits` in arbitrary precision.
+    pub fn max_joltage_big(digs: &[u8], max_digits: usize) -> Result<BigUint> {
+        let selected = JoltageBank::select_positions(digs, max_digits)?;
+        let kept: Vec<u8> = selected.into_iter().map(|i| digs[i]).collect();
+        Ok(BigUint::from_radix_be(&kept, 10).unwrap_or_default())
+    }
+
+    /// Sum of maximum joltages of all banks in `data`.
+    pub fn total_joltage(data: &str, max_digits: usize) -> Result<BigUint> {
+        let mut total = BigUint::ZERO;
+        for (i, line) in data.lines().enumerate() {
+            let digits = JoltageBank::parse_line(line);
+            total += JoltageBank::max_joltage_big(&digits, max_digits)
+                .map_err(|e| anyhow!("bank {}: {e}", i + 1))?;
+        }
+        Ok(total)
+    }
+
+    /// O(banks * budget * max_digits)
+    ///
+    /// Shares `budget` batteries between all banks of `data`, every bank
+    /// keeps from `min_digits` to `max_digits` of them. Maximizes summed
+    /// joltage with knapsack DP over best values of every bank for every
+    /// amount of digits.
+    pub fn allocate_budget(
+        data: &str,
+        budget: usize,
+        min_digits: usize,
+        max_digits: usize,
+    ) -> Result<Allocation> {
+        let banks: Vec<Vec<u8>> = data.lines().map(JoltageBank::parse_line).collect();
+
+        // best[i][k - min_digits]: maximum joltage of bank `i` with `k` digits
+        let mut best: Vec<Vec<BigUint>> = Vec::with_capacity(banks.len());
+        for (i, digs) in banks.iter().enumerate() {
+            let most = max_digits.min(digs.len());
+            if most < min_digits {
+                return Err(anyhow!(
+                    "bank {}: {} batteries are less than minimum {min_digits}",
+                    i + 1,
+                    digs.len()
+                ));
+            }
+            best.push(
+                (min_digits..=most)
+                    .map(|k| JoltageBank::max_joltage_big(digs, k))
+                    .collect::<Result<_>>()?,
+            );
+        }
+
+        // sums[b]: best total with exactly `b` batteries used so far
+        let mut sums: Vec<Option<BigUint>> = vec![None; budget + 1];
+        sums[0] = Some(BigUint::ZERO);
+        let mut choices: Vec<Vec<usize>> = Vec::with_capacity(banks.len());
+        for values in best.iter() {
+            let mut next: Vec<Option<BigUint>> = vec![None; budget + 1];
+            let mut choice = vec![0; budget + 1];
+            for (b, sum) in sums.iter().enumerate() {
+                let Some(sum) = sum else { continue };
+                for (off, value) in values.iter().enumerate() {
+                    let k = min_digits + off;
+                    if b + k > budget {
+                        break;
+                    }
+                    let candidate = sum + value;
+                    if next[b + k].as_ref().is_none_or(|n| candidate > *n) {
+                        next[b + k] = Some(candidate);
+                        choice[b + k] = k;
+                    }
+                }
+            }
+            sums = next;
+            choices.push(choice);
+        }
+
+        let (mut b, total) = sums
+            .into_iter()
+            .enumerate()
+            .filter_map(|(b, sum)| sum.map(|s| (b, s)))
+            .max_by(|x, y| x.1.cmp(&y.1).then(y.0.cmp(&x.0)))
+            .ok_or(anyhow!(
+                "budget {budget} is less than {} banks of {min_digits} digits",
+                banks.len()
+            ))?;
+
+        let mut digits = vec![0; banks.len()];
+        for i in (0..banks.len()).rev() {
+            digits[i] = choices[i][b];
+            b -= digits[i];
+        }
+        let values = digits
+            .iter()
+            .zip(&best)
+            .map(|(&k, values)| values[k - min_digits].clone())
+            .collect();
+
+        Ok(Allocation {
+            digits,
+            values,
+            total,
+        })
+    }
+
+    /// O(digs.len()^2 * max_digits)
+    ///
+    /// Ascending positions of `max_digits` batteries giving the largest
+    /// (or the smallest, leading zeros allowed) joltage under `cons`.
+    ///
+    /// Checks from the right which positions can still be completed,
+    /// then greedily takes the best digit among all positions that
+    /// continue the best prefix found so far.
+    pub fn select_constrained(
+        digs: &[u8],
+        max_digits: usize,
+        cons: &Constraints,
+        largest: bool,
+    ) -> Result<Vec<usize>> {
+        let n = digs.len();
+        let k = max_digits;
+        let (excluded, next_forced) = cons.masks(n)?;
+
+        if k == 0 {
+            return match next_forced[0] == n {
+                true => Ok(Vec::new()),
+                false => Err(anyhow!("forced positions do not fit into 0 digits")),
+            };
+        }
+
+        // positions which may follow `prev` (None for the first one)
+        let next = |prev: Option<usize>| {
+            let from = prev.map_or(0, |p| p + 1);
+            let mut to = next_forced[from].min(n.saturating_sub(1));
+            if let (Some(p), Some(gap)) = (prev, cons.max_gap) {
+                to = to.min(p + gap + 1);
+            }
+            (from..=to).filter(|&j| j < n && !excluded[j])
+        };
+
+        // done[r][j]: after keeping `j` the next `r` batteries can be kept
+        let mut done = vec![vec![false; n]; k];
+        for j in 0..n {
+            done[0][j] = next_forced[j + 1] == n;
+        }
+        for r in 1..k {
+            for j in 0..n {
+                done[r][j] = next(Some(j)).any(|j2| done[r - 1][j2]);
+            }
+        }
+
+        // every step keeps (position, index of previous step) for best prefixes
+        let mut steps: Vec<Vec<(usize, usize)>> = Vec::with_capacity(k);
+        let mut frontier: Vec<Option<usize>> = vec![None];
+        for t in 0..k {
+            let mut seen = vec![false; n];
+            let mut candidates: Vec<(usize, usize)> = Vec::new();
+            for (idx, &prev) in frontier.iter().enumerate() {
+                for j in next(prev).filter(|&j| done[k - t - 1][j]) {
+                    if !seen[j] {
+                        seen[j] = true;
+                        candidates.push((j, idx));
+                    }
+                }
+            }
+
+            let best = match largest {
+                true => candidates.iter().map(|&(j, _)| digs[j]).max(),
+                false => candidates.iter().map(|&(j, _)| digs[j]).min(),
+            }
+            .ok_or(anyhow!(
+                "no selection of {k} batteries satisfies constraints"
+            ))?;
+            candidates.retain(|&(j, _)| digs[j] == best);
+
+            frontier = candidates.iter().map(|&(j, _)| Some(j)).collect();
+            steps.push(candidates);
+        }
+
+        // walk parents back from any of the best positions
+        let mut selected = Vec::with_capacity(k);
+        let mut idx = 0;
+        for step in steps.iter().rev() {
+            let (j, parent) = step[idx];
+            selected.push(j);
+            idx = parent;
+        }
+        selected.reverse();
+        Ok(selected)
+    }
+
+    /// Reference for `select_constrained`, tries every combination.
+    pub fn select_constrained_slow(
+        digs: &[u8],
+        max_digits: usize,
+        cons: &Constraints,
+        largest: bool,
+    ) -> Option<String> {
+        let values = (0..digs.len())
+            .combinations(max_digits)
+            .filter(|comb| cons.allows(comb))
+            .map(|comb| comb.iter().map(|&i| (b'0' + digs[i]) as char).collect());
+        match largest {
+            true => values.max(),
+            false => values.min(),
+        }
+    }
+
+    /// O(digs.len())
+    ///
+    /// Minimum joltage for any `max_digits` as decimal digits,
+    /// leading zeros are allowed.
+    pub fn min_joltage_digits(digs: &[u8], max_digits: usize) -> Result<String> {
+        let n = digs.len();
+        let k = max_digits;
+        if n < k {
+            return Err(anyhow!("bank of {n} batteries is shorter than {k} digits"));
+        }
+
+        let mut stack: Vec<u8> = Vec::with_capacity(k);
         let mut to_remove = n - k;
 
         for &d in digs {
-            while to_remove > 0 && !stack.is_empty() && *stack.last().unwrap() < d {
+            while to_remove > 0 && !stack.is_empty() && *stack.last().unwrap() > d {
                 stack.pop();
                 to_remove -= 1;
             }
@@ -40,8 +389,106 @@ impl JoltageBank {
         }
 
         stack.truncate(k);
+        Ok(stack.into_iter().map(|d| (b'0' + d) as char).collect())
+    }
+
+    /// O(digs.len() * max_digits)
+    ///
+    /// `nth` largest (starting from 1) distinct joltage of `max_digits` digits.
+    ///
+    /// Counts distinct subsequences of every length for every suffix,
+    /// then skips whole groups of values sharing the same next digit.
+    pub fn nth_max_joltage_digits(digs: &[u8], max_digits: usize, nth: u128) -> Result<String> {
+        let n = digs.len();
+        let k = max_digits;
+        if nth == 0 {
+            return Err(anyhow!("joltages are numbered from 1"));
+        }
 
-        stack.iter().fold(0, |acc, &d| 10 * acc + d as u64)
+        // first position of every digit at or after `i`
+        let mut first = vec![[n; 10]; n + 1];
+        for i in (0..n).rev() {
+            first[i] = first[i + 1];
+            first[i][digs[i] as usize] = i;
+        }
+
+        // count[r][i]: distinct subsequences of length `r` in `digs[i..]`
+        let mut count = vec![vec![0u128; n + 1]; k + 1];
+        count[0] = vec![1; n + 1];
+        for r in 1..=k {
+            for i in 0..n {
+                count[r][i] = first[i]
+                    .iter()
+                    .filter(|&&p| p < n)
+                    .fold(0u128, |acc, &p| acc.saturating_add(count[r - 1][p + 1]));
+            }
+        }
+
+        if nth > count[k][0] {
+            return Err(anyhow!(
+                "there are only {} distinct joltages of {k} digits",
+                count[k][0]
+            ));
+        }
+
+        // every step has some digit whose group contains `nth`
+        let mut nth = nth;
+        let mut pos = 0;
+        let mut result = String::with_capacity(k);
+        for t in 0..k {
+            for d in (0..10).rev() {
+                let p = first[pos][d];
+                if p == n {
+                    continue;
+                }
+                let c = count[k - t - 1][p + 1];
+                if nth <= c {
+                    result.push((b'0' + d as u8) as char);
+                    pos = p + 1;
+                    break;
+                }
+                nth -= c;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Reference for `nth_max_joltage_digits`, tries every combination.
+    pub fn nth_max_joltage_slow(digs: &[u8], max_digits: usize, nth: usize) -> Option<String> {
+        let values: BTreeSet<String> = digs
+            .iter()
+            .combinations(max_digits)
+            .map(|comb| comb.iter().map(|&&d| (b'0' + d) as char).collect())
+            .collect();
+        values.into_iter().rev().nth(nth.checked_sub(1)?)
+    }
+
+    /// Renders bank with `^` under every kept battery and resulting joltage.
+    pub fn explain(digs: &[u8], max_digits: usize) -> Result<String> {
+        let selected = JoltageBank::select_positions(digs, max_digits)?;
+
+        let bank: String = digs.iter().map(|&d| (b'0' + d) as char).collect();
+        let mut marks = vec![' '; digs.len()];
+        for &i in selected.iter() {
+            marks[i] = '^';
+        }
+        let marks: String = marks.into_iter().collect();
+        let value = JoltageBank::max_joltage_digits(digs, max_digits)?;
+
+        Ok(format!("{bank}\n{}\t{value}", marks.trim_end()))
+    }
+
+    fn parse_line(line: &str) -> Vec<u8> {
+        line.trim().as_bytes().iter().map(|&b| b - b'0').collect()
+    }
+
+    /// Explanation of every bank in `data`, see `explain`.
+    pub fn explain_all(data: &str, max_digits: usize) -> Result<String> {
+        Ok(data
+            .lines()
+            .map(|line| JoltageBank::explain(&JoltageBank::parse_line(line), max_digits))
+            .collect::<Result<Vec<_>>>()?
+            .join("\n"))
     }
 }
 
@@ -51,27 +498,17 @@ impl Exercise for JoltageBank {
     }
 
     fn part1(&mut self, data: &str) -> String {
-        let mut result = 0;
-        for line in data.lines() {
-            let digits: Vec<_> = line.trim().as_bytes().iter().map(|&b| b - b'0').collect();
-            result += JoltageBank::max_joltage_slow(&digits, 2);
-        }
-        result.to_string()
+        JoltageBank::total_joltage(data, 2).unwrap().to_string()
     }
 
     fn part2(&mut self, data: &str) -> String {
-        let mut result = 0;
-        for line in data.lines() {
-            let digits: Vec<_> = line.trim().as_bytes().iter().map(|&b| b - b'0').collect();
-            result += JoltageBank::max_joltage_fast(&digits, 12);
-        }
-        result.to_string()
+        JoltageBank::total_joltage(data, 12).unwrap().to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils;
+    use crate::utils::{self, Rng};
 
     use super::*;
 
@@ -90,4 +527,152 @@ mod tests {
         let res = bank.part2(&data);
         assert_eq!(res, "3121910778619");
     }
+
+    #[test]
+    fn third_test_fast_matches_slow() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for _ in 0..500 {
+            let n = 1 + rng.next(12) as usize;
+            let k = 1 + rng.next(n.min(5) as u64);
+            let digs: Vec<u8> = (0..n).map(|_| rng.next(10) as u8).collect();
+
+            let (selected, value) = JoltageBank::select_fast(&digs, k).unwrap();
+            assert_eq!(
+                value,
+                JoltageBank::max_joltage_slow(&digs, k),
+                "{digs:?} {k}"
+            );
+            assert_eq!(selected.len(), k as usize);
+            assert!(selected.windows(2).all(|w| w[0] < w[1]));
+            let picked = selected.iter().fold(0, |acc, &i| acc * 10 + digs[i] as u64);
+            assert_eq!(picked, value);
+        }
+    }
+
+    #[test]
+    fn third_test_explain() {
+        let explained = JoltageBank::explain(&[8, 1, 8, 1, 8, 1, 9, 1], 2).unwrap();
+        assert_eq!(explained, "81818191\n      ^^\t91");
+    }
+
+    #[test]
+    fn third_test_long_values() {
+        let digs: Vec<u8> = (0..60).map(|i| (i * 7 % 10) as u8).collect();
+        for k in [1, 19, 20, 45, 60] {
+            let text = JoltageBank::max_joltage_digits(&digs, k).unwrap();
+            let big = JoltageBank::max_joltage_big(&digs, k).unwrap();
+            assert_eq!(text.len(), k);
+            assert_eq!(big.to_string(), text.trim_start_matches('0'));
+        }
+        let fast = JoltageBank::max_joltage_fast(&digs, 19).unwrap();
+        assert_eq!(
+            fast.to_string(),
+            JoltageBank::max_joltage_digits(&digs, 19).unwrap()
+        );
+
+        assert!(JoltageBank::max_joltage_fast(&digs, 20).is_err());
+        assert!(JoltageBank::max_joltage_big(&digs, 61).is_err());
+        assert!(JoltageBank::total_joltage("123\n45", 3).is_err());
+        assert_eq!(
+            JoltageBank::total_joltage("99999999999999999999\n1", 1).unwrap(),
+            BigUint::from(10u32)
+        );
+        assert_eq!(
+            JoltageBank::total_joltage("99999999999999999999\n99999999999999999999", 20)
+                .unwrap()
+                .to_string(),
+            "199999999999999999998"
+        );
+    }
+
+    fn digits_of(digs: &[u8], selected: &[usize]) -> String {
+        selected.iter().map(|&i| (b'0' + digs[i]) as char).collect()
+    }
+
+    #[test]
+    fn third_test_constrained_matches_slow() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for _ in 0..2000 {
+            let n = 1 + rng.next(10) as usize;
+            let k = rng.next(n as u64 + 1) as usize;
+            let digs: Vec<u8> = (0..n).map(|_| rng.next(10) as u8).collect();
+            let mut cons = Constraints::default();
+            for _ in 0..rng.next(3) {
+                cons.include.push(rng.next(n as u64) as usize);
+            }
+            for _ in 0..rng.next(3) {
+                cons.exclude.push(rng.next(n as u64) as usize);
+            }
+            if rng.next(2) == 0 {
+                cons.max_gap = Some(rng.next(4) as usize);
+            }
+
+            for largest in [true, false] {
+                let fast = JoltageBank::select_constrained(&digs, k, &cons, largest);
+                let slow = JoltageBank::select_constrained_slow(&digs, k, &cons, largest);
+                match (fast, slow) {
+                    (Ok(selected), Some(value)) => {
+                        assert!(cons.allows(&selected), "{digs:?} {k} {cons:?}");
+                        assert_eq!(selected.len(), k);
+                        assert_eq!(digits_of(&digs, &selected), value, "{digs:?} {k} {cons:?}");
+                    }
+                    (Err(_), None) => {}
+                    (fast, slow) => panic!("{digs:?} {k} {cons:?}: {fast:?} != {slow:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn third_test_min_and_nth_match_slow() {
+        let mut rng = Rng(0xdeadbeefcafe);
+        for _ in 0..500 {
+            let n = 1 + rng.next(10) as usize;
+            let k = rng.next(n as u64 + 1) as usize;
+            let digs: Vec<u8> = (0..n).map(|_| rng.next(4) as u8).collect();
+
+            let min =
+                JoltageBank::select_constrained_slow(&digs, k, &Constraints::default(), false);
+            assert_eq!(JoltageBank::min_joltage_digits(&digs, k).ok(), min);
+
+            for nth in 0..=6 {
+                let slow = JoltageBank::nth_max_joltage_slow(&digs, k, nth);
+                let fast = JoltageBank::nth_max_joltage_digits(&digs, k, nth as u128).ok();
+                assert_eq!(fast, slow, "{digs:?} {k} {nth}");
+            }
+        }
+    }
+
+    #[test]
+    fn third_test_budget_matches_brute_force() {
+        let data = utils::read_data(3, "test1").unwrap();
+        let banks: Vec<Vec<u8>> = data.lines().map(JoltageBank::parse_line).collect();
+
+        for (budget, min, max) in [(8, 1, 3), (4, 1, 3), (20, 2, 6), (60, 12, 15), (7, 0, 2)] {
+            let alloc = JoltageBank::allocate_budget(&data, budget, min, max).unwrap();
+
+            let brute = (0..banks.len())
+                .map(|_| min..=max)
+                .multi_cartesian_product()
+                .filter(|ks| ks.iter().sum::<usize>() <= budget)
+                .map(|ks| {
+                    ks.iter()
+                        .zip(&banks)
+                        .map(|(&k, digs)| JoltageBank::max_joltage_big(digs, k).unwrap())
+                        .sum::<BigUint>()
+                })
+                .max()
+                .unwrap();
+
+            assert_eq!(alloc.total, brute, "{budget} {min} {max}");
+            assert!(alloc.digits.iter().sum::<usize>() <= budget);
+            assert!(alloc.digits.iter().all(|k| (min..=max).contains(k)));
+            assert_eq!(alloc.values.iter().sum::<BigUint>(), alloc.total);
+        }
+
+        let alloc = JoltageBank::allocate_budget(&data, 48, 12, 12).unwrap();
+        assert_eq!(alloc.total.to_string(), "3121910778619");
+        assert!(JoltageBank::allocate_budget(&data, 3, 1, 2).is_err());
+        assert!(JoltageBank::allocate_budget(&data, 100, 16, 20).is_err());
+    }
 }
@@ -1,5 +1,5 @@
 use anyhow::{Result, anyhow};
-use std::{fs::File, io::Read};
+use std::{fs::File, io::Read, ops::RangeInclusive, thread};
 
 /// Main trait for Advent of Code exercises.
 pub trait Exercise {
@@ -21,6 +21,96 @@ pub fn read_data(ex_num: u8, filename: &str) -> Result<String> {
     Ok(data)
 }
 
+/// Sums `f(i)` for every value of `ranges` on `threads` scoped threads.
+///
+/// Overlapping ranges are summed twice.
+pub fn parallel_range_sum<F>(ranges: &[RangeInclusive<u64>], threads: usize, f: F) -> u128
+where
+    F: Fn(u64) -> u128 + Sync,
+{
+    parallel_range_fold(ranges, threads, || 0, |acc, i| acc + f(i), |a, b| a + b)
+}
+
+/// Folds every value of `ranges` with `fold` on `threads` scoped threads.
+///
+/// Ranges are cut into chunks of nearly equal length, so threads get
+/// the same amount of values whatever the sizes of the ranges are.
+/// Every chunk starts from `init()` and results are merged in range order,
+/// so the result does not depend on thread timing.
+pub fn parallel_range_fold<T, I, F, M>(
+    ranges: &[RangeInclusive<u64>],
+    threads: usize,
+    init: I,
+    fold: F,
+    merge: M,
+) -> T
+where
+    T: Send,
+    I: Fn() -> T + Sync,
+    F: Fn(T, u64) -> T + Sync,
+    M: Fn(T, T) -> T,
+{
+    let threads = threads.max(1);
+    let total: u128 = ranges
+        .iter()
+        .filter(|r| !r.is_empty())
+        .map(|r| (*r.end() - *r.start()) as u128 + 1)
+        .sum();
+    let chunk = total.div_ceil(threads as u128).max(1);
+
+    // split ranges into chunks of `chunk` values
+    let mut chunks: Vec<Vec<RangeInclusive<u64>>> = vec![Vec::new()];
+    let mut room = chunk;
+    for r in ranges.iter().filter(|r| !r.is_empty()) {
+        let (mut beg, end) = (*r.start(), *r.end());
+        loop {
+            let left = (end - beg) as u128 + 1;
+            if left <= room {
+                chunks.last_mut().unwrap().push(beg..=end);
+                room -= left;
+                break;
+            }
+            let mid = beg + (room - 1) as u64;
+            chunks.last_mut().unwrap().push(beg..=mid);
+            chunks.push(Vec::new());
+            room = chunk;
+            beg = mid + 1;
+        }
+        if room == 0 {
+            chunks.push(Vec::new());
+            room = chunk;
+        }
+    }
+
+    let (init, fold) = (&init, &fold);
+    thread::scope(|s| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|part| s.spawn(move || part.into_iter().flatten().fold(init(), fold)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .reduce(&merge)
+            .unwrap()
+    })
+}
+
+/// Xorshift generator for randomized tests.
+#[cfg(test)]
+pub struct Rng(pub u64);
+
+#[cfg(test)]
+impl Rng {
+    /// Next value in range [0, bound).
+    pub fn next(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,4 +121,42 @@ mod tests {
         assert!(data.starts_with("L68"));
         Ok(())
     }
+
+    #[test]
+    fn test_parallel_range_sum() {
+        let ranges = [
+            0..=0,
+            RangeInclusive::new(5, 4),
+            1..=1000,
+            10..=17,
+            u64::MAX - 2..=u64::MAX,
+        ];
+        let expected: u128 = ranges.iter().cloned().flatten().map(u128::from).sum();
+        for threads in 0..=9 {
+            assert_eq!(parallel_range_sum(&ranges, threads, u128::from), expected);
+        }
+        assert_eq!(parallel_range_sum(&[], 4, u128::from), 0);
+    }
+
+    #[test]
+    fn test_parallel_range_fold() {
+        let ranges = [3..=9, 20..=25, 1..=2];
+        let expected: Vec<u64> = ranges.iter().cloned().flatten().collect();
+        for threads in 1..=7 {
+            let values = parallel_range_fold(
+                &ranges,
+                threads,
+                Vec::new,
+                |mut acc, i| {
+                    acc.push(i);
+                    acc
+                },
+                |mut a, b| {
+                    a.extend(b);
+                    a
+                },
+            );
+            assert_eq!(values, expected);
+        }
+    }
 }